@@ -21,6 +21,8 @@
 #include <unistd.h>
 
 #include <atomic>
+#include <map>
+#include <mutex>
 #include <string>
 
 #define LOG_TAG "AppMidiManager-JNI"
@@ -29,22 +31,32 @@
 #include "AndroidDebug.h"
 #include "MidiSpec.h"
 
-static AMidiDevice* sNativeReceiveDevice = NULL;
-// The thread only reads this value, so no special protection is required.
-static AMidiOutputPort* sMidiOutputPort = NULL;
+// One per open "output" device, each polled on its own thread so several controllers can
+// play at once and one can be unplugged without stopping the others.
+struct MidiReader {
+  int32_t deviceId;
+  AMidiDevice* device;
+  AMidiOutputPort* port;
+  pthread_t thread;
+  std::atomic<bool> reading;
+};
 
+static std::mutex sReadersLock;
+static std::map<int32_t, MidiReader*> sReaders;
+
+// Guards the send port, writes come from the rust output thread while the device can be
+// closed from the java main thread.
+static std::mutex sWriteLock;
 static AMidiDevice* sNativeSendDevice = NULL;
 static AMidiInputPort* sMidiInputPort = NULL;
 
-static pthread_t sReadThread;
-static std::atomic<bool> sReading(false);
-
 // The Data Callback
 extern JavaVM* theJvm;           // Need this for allocating data buffer for...
 extern jobject dataCallbackObj;  // This is the (Java) object that implements...
 extern jmethodID midDataCallback;  // ...this callback routine
 
-static void SendTheReceivedData(uint8_t* data, int numBytes) {
+static void SendTheReceivedData(uint8_t* data, int numBytes, int64_t timestamp,
+                                int32_t deviceId) {
   JNIEnv* env;
   theJvm->AttachCurrentThread(&env, NULL);
   if (env == NULL) {
@@ -56,7 +68,9 @@ static void SendTheReceivedData(uint8_t* data, int numBytes) {
   env->SetByteArrayRegion(ret, 0, numBytes, (jbyte*)data);
 
   // send it to the (Java) callback
-  env->CallVoidMethod(dataCallbackObj, midDataCallback, ret);
+  env->CallVoidMethod(dataCallbackObj, midDataCallback, ret, (jlong)timestamp,
+                      (jint)deviceId);
+  env->DeleteLocalRef(ret);
 }
 
 #if 0
@@ -89,16 +103,13 @@ static void logMidiBuffer(int64_t timestamp, uint8_t* dataBytes, size_t numDataB
  * application-provided (Java) callback.
  */
 static void* readThreadRoutine(void* context) {
-  (void)context;  // unused
-
-  sReading = true;
-  // AMidiOutputPort* outputPort = sMidiOutputPort.load();
-  AMidiOutputPort* outputPort = sMidiOutputPort;
+  MidiReader* reader = (MidiReader*)context;
+  AMidiOutputPort* outputPort = reader->port;
 
   const size_t MAX_BYTES_TO_RECEIVE = 128;
   uint8_t incomingMessage[MAX_BYTES_TO_RECEIVE];
 
-  while (sReading) {
+  while (reader->reading) {
     // AMidiOutputPort_receive is non-blocking, so let's not burn up the CPU
     // unnecessarily
     usleep(2000);
@@ -113,20 +124,24 @@ static void* readThreadRoutine(void* context) {
     if (numMessagesReceived < 0) {
       LOGW("Failure receiving MIDI data %zd", numMessagesReceived);
       // Exit the thread
-      sReading = false;
+      reader->reading = false;
     }
     if (numMessagesReceived > 0 && numBytesReceived >= 0) {
-      if (opcode == AMIDI_OPCODE_DATA &&
-          (incomingMessage[0] & kMIDISysCmdChan) != kMIDISysCmdChan) {
+      // everything goes through, system messages included. a packet can start in the middle
+      // of a SysEx or with a realtime byte in front of a note, the rust side's parser sorts
+      // the stream out.
+      if (opcode == AMIDI_OPCODE_DATA) {
         // (optionally) Dump to log
         // logMidiBuffer(timestamp, incomingMessage, numBytesReceived);
-        SendTheReceivedData(incomingMessage, numBytesReceived);
+        SendTheReceivedData(incomingMessage, numBytesReceived, timestamp,
+                            reader->deviceId);
       } else if (opcode == AMIDI_OPCODE_FLUSH) {
         // ignore
       }
     }
-  }  // end while(sReading)
+  }  // end while(reader->reading)
 
+  theJvm->DetachCurrentThread();
   return NULL;
 }
 
@@ -137,43 +152,63 @@ extern "C" {
 
 /**
  * Native implementation of TBMidiManager.startReadingMidi() method.
- * Opens the first "output" port from specified MIDI device for sReading.
+ * Opens an "output" port from specified MIDI device and starts a thread reading it.
  * @param   env  JNI Env pointer.
  * @param   (unnamed)   TBMidiManager (Java) object.
  * @param   midiDeviceObj   (Java) MidiDevice object.
  * @param   portNumber      The index of the "output" port to open.
+ * @param   deviceId        The MidiDeviceInfo id, passed back with every message.
  */
 void Java_co_realfit_example_AppMidiManager_startReadingMidi(
-    JNIEnv* env, jobject, jobject midiDeviceObj, jint portNumber) {
-  AMidiDevice_fromJava(env, midiDeviceObj, &sNativeReceiveDevice);
-  // int32_t deviceType = AMidiDevice_getType(sNativeReceiveDevice);
-  // ssize_t numPorts = AMidiDevice_getNumOutputPorts(sNativeReceiveDevice);
+    JNIEnv* env, jobject, jobject midiDeviceObj, jint portNumber, jint deviceId) {
+  std::lock_guard<std::mutex> lock(sReadersLock);
+  if (sReaders.count(deviceId) != 0) {
+    return;
+  }
 
-  AMidiOutputPort* outputPort;
-  AMidiOutputPort_open(sNativeReceiveDevice, portNumber, &outputPort);
+  MidiReader* reader = new MidiReader();
+  reader->deviceId = deviceId;
+  reader->reading = true;
 
-  // sMidiOutputPort.store(outputPort);
-  sMidiOutputPort = outputPort;
+  if (AMidiDevice_fromJava(env, midiDeviceObj, &reader->device) != AMEDIA_OK ||
+      AMidiOutputPort_open(reader->device, portNumber, &reader->port) != AMEDIA_OK) {
+    LOGE("Could not open MIDI device %d for reading", deviceId);
+    if (reader->device != NULL) {
+      AMidiDevice_release(reader->device);
+    }
+    delete reader;
+    return;
+  }
 
-  // Start read thread
-  // pthread_init(true);
-  /*int pthread_result =*/pthread_create(&sReadThread, NULL, readThreadRoutine,
-                                         NULL);
+  sReaders[deviceId] = reader;
+  pthread_create(&reader->thread, NULL, readThreadRoutine, reader);
 }
 
 /**
  * Native implementation of the (Java) TBMidiManager.stopReadingMidi() method.
  * @param   (unnamed)   JNI Env pointer.
  * @param   (unnamed)   TBMidiManager (Java) object.
+ * @param   deviceId    The device to stop reading.
  */
-void Java_co_realfit_example_AppMidiManager_stopReadingMidi(JNIEnv*,
-                                                                jobject) {
-  // need some synchronization here
-  sReading = false;
-  pthread_join(sReadThread, NULL);
+void Java_co_realfit_example_AppMidiManager_stopReadingMidi(JNIEnv*, jobject,
+                                                            jint deviceId) {
+  MidiReader* reader;
+  {
+    std::lock_guard<std::mutex> lock(sReadersLock);
+    auto it = sReaders.find(deviceId);
+    if (it == sReaders.end()) {
+      return;
+    }
+    reader = it->second;
+    sReaders.erase(it);
+  }
+
+  reader->reading = false;
+  pthread_join(reader->thread, NULL);
 
-  /*media_status_t status =*/AMidiDevice_release(sNativeReceiveDevice);
-  sNativeReceiveDevice = NULL;
+  AMidiOutputPort_close(reader->port);
+  /*media_status_t status =*/AMidiDevice_release(reader->device);
+  delete reader;
 }
 
 /*
@@ -189,6 +224,7 @@ void Java_co_realfit_example_AppMidiManager_stopReadingMidi(JNIEnv*,
  */
 void Java_co_realfit_example_AppMidiManager_startWritingMidi(
     JNIEnv* env, jobject, jobject midiDeviceObj, jint portNumber) {
+  std::lock_guard<std::mutex> lock(sWriteLock);
   AMidiDevice_fromJava(env, midiDeviceObj, &sNativeSendDevice);
   // int32_t deviceType = AMidiDevice_getType(sNativeReceiveDevice);
   // ssize_t numPorts = AMidiDevice_getNumInputPorts(sNativeSendDevice);
@@ -206,8 +242,15 @@ void Java_co_realfit_example_AppMidiManager_startWritingMidi(
  */
 void Java_co_realfit_example_AppMidiManager_stopWritingMidi(JNIEnv*,
                                                                 jobject) {
-  /*media_status_t status =*/AMidiDevice_release(sNativeSendDevice);
-  sNativeSendDevice = NULL;
+  std::lock_guard<std::mutex> lock(sWriteLock);
+  if (sMidiInputPort != NULL) {
+    AMidiInputPort_close(sMidiInputPort);
+    sMidiInputPort = NULL;
+  }
+  if (sNativeSendDevice != NULL) {
+    /*media_status_t status =*/AMidiDevice_release(sNativeSendDevice);
+    sNativeSendDevice = NULL;
+  }
 }
 
 /**
@@ -221,6 +264,12 @@ void Java_co_realfit_example_AppMidiManager_stopWritingMidi(JNIEnv*,
 void Java_co_realfit_example_AppMidiManager_writeMidi(JNIEnv* env, jobject,
                                                           jbyteArray data,
                                                           jint numBytes) {
+  std::lock_guard<std::mutex> lock(sWriteLock);
+  // nothing to write to until a send device has been opened
+  if (sMidiInputPort == NULL) {
+    return;
+  }
+
   jbyte* bufferPtr = env->GetByteArrayElements(data, NULL);
   /*ssize_t numSent =*/AMidiInputPort_send(sMidiInputPort, (uint8_t*)bufferPtr,
                                            numBytes);
@@ -36,7 +36,7 @@ JNICALL void Java_co_realfit_example_MainActivity_initNative(
       env->FindClass("co/realfit/example/MainActivity");
   dataCallbackObj = env->NewGlobalRef(instance);
   midDataCallback =
-      env->GetMethodID(clsMainActivity, "onNativeMessageReceive", "([B)V");
+      env->GetMethodID(clsMainActivity, "onNativeMessageReceive", "([BJI)V");
 }
 
 }  // extern "C"
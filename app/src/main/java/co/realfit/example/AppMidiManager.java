@@ -23,19 +23,27 @@ import android.media.midi.MidiManager;
 import android.media.midi.MidiInputPort;
 import android.util.Log;
 
+import java.io.IOException;
 import java.util.ArrayList;
+import java.util.HashMap;
+import java.util.HashSet;
 import java.util.List;
 
 public class AppMidiManager {
     private static final String TAG = AppMidiManager.class.getName();
+    private static final int NO_DEVICE = -1;
 
     private MidiManager mMidiManager;
 
-    // Selected Device(s)
-    private MidiDevice mReceiveDevice; // an "Output" device is one we will RECEIVE data FROM
+    // Connected devices, by MidiDeviceInfo id
+    private HashSet<Integer> mConnected = new HashSet<Integer>();
+    // Open "Output" devices, ones we RECEIVE data FROM, each read on its own native thread
+    private HashMap<Integer, MidiDevice> mReceiveDevices = new HashMap<Integer, MidiDevice>();
 
     private MidiDevice mSendDevice; // an "Input" device is one we will SEND data TO
     private MidiInputPort mSendPort;
+    // The device being opened for sending, until OpenMidiSendDeviceListener hears back
+    private int mPendingSendId = NO_DEVICE;
 
     private boolean mUseRunningStatus = true;
 
@@ -63,11 +71,10 @@ public class AppMidiManager {
                     devInfo.getProperties().getString(MidiDeviceInfo.PROPERTY_NAME);
             if (deviceName == null) {
                 continue;
-            } else {
-                Log.i(TAG, deviceName);
-                this.openReceiveDevice(devInfo);
             }
 
+            deviceAdded(devInfo);
+
             if (numInPorts > 0) {
                 sendDevices.add(devInfo);
             }
@@ -79,18 +86,81 @@ public class AppMidiManager {
         }
     }
 
+    /**
+     * Registers a newly attached device with rust and opens it. Calling it again for a device
+     * that is already known does nothing.
+     * @param devInfo The device that was attached.
+     */
+    public void deviceAdded(MidiDeviceInfo devInfo) {
+        int id = devInfo.getId();
+        if (!mConnected.add(id)) {
+            return;
+        }
+
+        String manufacturer =
+                devInfo.getProperties().getString(MidiDeviceInfo.PROPERTY_MANUFACTURER);
+        String name = devInfo.getProperties().getString(MidiDeviceInfo.PROPERTY_NAME);
+        Log.i(TAG, "MIDI device added: " + name);
+        midiDeviceAdded(id, manufacturer != null ? manufacturer : "", name != null ? name : "",
+                devInfo.getInputPortCount(), devInfo.getOutputPortCount());
+
+        if (devInfo.getOutputPortCount() > 0) {
+            openReceiveDevice(devInfo);
+        }
+
+        // MIDI out goes to the first device that can take it
+        if (devInfo.getInputPortCount() > 0) {
+            openSendDeviceIfNone(devInfo);
+        }
+    }
+
+    /**
+     * Stops reading from and writing to a detached device and tells rust it is gone.
+     * @param devInfo The device that was detached.
+     */
+    public void deviceRemoved(MidiDeviceInfo devInfo) {
+        int id = devInfo.getId();
+        if (!mConnected.remove(id)) {
+            return;
+        }
+
+        Log.i(TAG, "MIDI device removed: "
+                + devInfo.getProperties().getString(MidiDeviceInfo.PROPERTY_NAME));
+        closeReceiveDevice(id);
+
+        // a device still being opened is closed when it arrives
+        if (mPendingSendId == id) {
+            mPendingSendId = NO_DEVICE;
+        }
+
+        if (mSendDevice != null && mSendDevice.getInfo().getId() == id) {
+            closeSendDevice();
+        }
+
+        midiDeviceRemoved(id);
+        openAnySendDevice();
+    }
+
     //
     // Receive Device
     //
     public class OpenMidiReceiveDeviceListener implements MidiManager.OnDeviceOpenedListener {
         @Override
         public void onDeviceOpened(MidiDevice device) {
-            Log.e(TAG, device.getInfo().getProperties().getString(MidiDeviceInfo.PROPERTY_NAME));
-//            if (device.getInfo().getProperties().getString(MidiDeviceInfo.PROPERTY_NAME).startsWith("Akai")) {
-//                mReceiveDevice = device;
-//                startReadingMidi(mReceiveDevice, 0/*mPortNumber*/);
-//            }
-            startReadingMidi(device, 0/*mPortNumber*/);
+            if (device == null) {
+                Log.e(TAG, "could not open MIDI device for reading");
+                return;
+            }
+
+            int id = device.getInfo().getId();
+            // it may have been unplugged while it was being opened
+            if (!mConnected.contains(id) || mReceiveDevices.containsKey(id)) {
+                closeQuietly(device);
+                return;
+            }
+
+            mReceiveDevices.put(id, device);
+            startReadingMidi(device, 0/*mPortNumber*/, id);
         }
     }
 
@@ -98,10 +168,20 @@ public class AppMidiManager {
         mMidiManager.openDevice(devInfo, new OpenMidiReceiveDeviceListener(), null);
     }
 
-    public void closeReceiveDevice() {
-        if (mReceiveDevice != null) {
+    public void closeReceiveDevice(int id) {
+        MidiDevice device = mReceiveDevices.remove(id);
+        if (device != null) {
             // Native API
-            mReceiveDevice = null;
+            stopReadingMidi(id);
+            closeQuietly(device);
+        }
+    }
+
+    private static void closeQuietly(MidiDevice device) {
+        try {
+            device.close();
+        } catch (IOException e) {
+            Log.w(TAG, "closing MIDI device failed", e);
         }
     }
 
@@ -111,18 +191,56 @@ public class AppMidiManager {
     public class OpenMidiSendDeviceListener implements MidiManager.OnDeviceOpenedListener {
         @Override
         public void onDeviceOpened(MidiDevice device) {
+            if (device == null) {
+                Log.e(TAG, "could not open MIDI device for writing");
+                mPendingSendId = NO_DEVICE;
+                return;
+            }
+
+            // it may have been unplugged while it was being opened
+            if (device.getInfo().getId() != mPendingSendId || mSendDevice != null) {
+                closeQuietly(device);
+                return;
+            }
+
+            mPendingSendId = NO_DEVICE;
             mSendDevice = device;
             startWritingMidi(mSendDevice, 0/*mPortNumber*/);
         }
     }
 
     public void openSendDevice(MidiDeviceInfo devInfo) {
+        mPendingSendId = devInfo.getId();
         mMidiManager.openDevice(devInfo, new OpenMidiSendDeviceListener(), null);
     }
 
+    /**
+     * Opens a device for sending unless one is already open or being opened.
+     * @param devInfo The device to send to, which must have input ports.
+     */
+    private void openSendDeviceIfNone(MidiDeviceInfo devInfo) {
+        if (mSendDevice == null && mPendingSendId == NO_DEVICE) {
+            openSendDevice(devInfo);
+        }
+    }
+
+    /**
+     * Falls back to sending to another connected device, after the one in use was detached.
+     */
+    private void openAnySendDevice() {
+        for (MidiDeviceInfo devInfo : mMidiManager.getDevices()) {
+            if (mConnected.contains(devInfo.getId()) && devInfo.getInputPortCount() > 0) {
+                openSendDeviceIfNone(devInfo);
+                return;
+            }
+        }
+    }
+
     public void closeSendDevice() {
         if (mSendDevice != null) {
             // Native API
+            stopWritingMidi();
+            closeQuietly(mSendDevice);
             mSendDevice = null;
         }
     }
@@ -182,13 +300,18 @@ public class AppMidiManager {
         System.loadLibrary("example");
     }
 
-    public native void startReadingMidi(MidiDevice receiveDevice, int portNumber);
-    public native void stopReadingMidi();
+    public native void startReadingMidi(MidiDevice receiveDevice, int portNumber, int deviceId);
+    public native void stopReadingMidi(int deviceId);
 
     public native void startWritingMidi(MidiDevice sendDevice, int portNumber);
     public native void stopWritingMidi();
     public native void writeMidi(byte[] data, int length);
-    public native void newMidiDev(String new_dev);
-    public native void sendMidiMessage(byte[] message);
-    public native void clearKnownDevs();
+    public native void midiDeviceAdded(int deviceId, String manufacturer, String name,
+                                       int inputPorts, int outputPorts);
+    public native void midiDeviceRemoved(int deviceId);
+    /**
+     * @param ageNanos  How long ago the device timestamped the message, in nanoseconds.
+     * @param deviceId  The MidiDeviceInfo id of the device it came from.
+     */
+    public native void sendMidiMessage(byte[] message, long ageNanos, int deviceId);
 }
@@ -107,11 +107,13 @@ public class MainActivity extends NativeActivity {
     private class MidiDeviceCallback extends MidiManager.DeviceCallback {
         @Override
         public void onDeviceAdded(MidiDeviceInfo device) {
+            mAppMidiManager.deviceAdded(device);
             ScanMidiDevices();
         }
 
         @Override
         public void onDeviceRemoved(MidiDeviceInfo device) {
+            mAppMidiManager.deviceRemoved(device);
             ScanMidiDevices();
         }
     }
@@ -152,13 +154,11 @@ public class MainActivity extends NativeActivity {
      */
     private void fillDeviceList(ArrayList<MidiDeviceInfo> devices) {
         midiDevices.clear();
-        mAppMidiManager.clearKnownDevs();
 
         for(MidiDeviceInfo devInfo : devices) {
 //            listItems.add(new MidiDeviceListItem(devInfo));
-            // add to "known devices" list and send device name to rust.
+            // rust hears about devices from AppMidiManager.deviceAdded/deviceRemoved
             midiDevices.add(devInfo);
-            mAppMidiManager.newMidiDev(devInfo.getProperties().getString(MidiDeviceInfo.PROPERTY_NAME));
         }
         // sendMidiMessagen
         // spinner.setAdapter(dataAdapter);
@@ -182,12 +182,24 @@ public class MainActivity extends NativeActivity {
     /**
      * Called from the native code when MIDI messages are received.
      * @param message
+     * @param timestamp When the device received it, on the System.nanoTime() clock.
+     * @param deviceId The MidiDeviceInfo id of the device it came from.
      */
-    private void onNativeMessageReceive(final byte[] message) {
+    private void onNativeMessageReceive(final byte[] message, final long timestamp,
+                                        final int deviceId) {
         //
-        // send midi messages to rust
+        // send midi messages to rust, with their age since both sides share the monotonic
+        // clock but not a way to pass an absolute time
         //
 
-        mAppMidiManager.sendMidiMessage(message);
+        mAppMidiManager.sendMidiMessage(message, System.nanoTime() - timestamp, deviceId);
+    }
+
+    /**
+     * Called from rust with MIDI to send to the output device.
+     * @param data One or more complete messages.
+     */
+    public void sendMidiOut(byte[] data) {
+        mAppMidiManager.writeMidi(data, data.length);
     }
 }
@@ -0,0 +1,71 @@
+//! compares the old per-sample render path (one `RwLock` write per sample, like the original
+//! output closure) against `SynthHost::render_block` at a few buffer sizes.
+//!
+//! run with `cargo bench -p synth-tab-core`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use std::sync::{Arc, RwLock};
+use stepper_synth_backend::pygame_coms::SynthEngineType;
+use stepper_synth_backend::synth_engines::{SynthChannel, SynthEngine};
+use stepper_synth_backend::{SampleGen, SAMPLE_RATE};
+use synth_tab_core::synth::{SynthCmd, SynthHost};
+
+const BUFFER_SIZES: [usize; 5] = [64, 128, 256, 512, 2048];
+/// a held chord so the engine has voices to render.
+const CHORD: [u8; 4] = [48, 55, 60, 64];
+
+fn per_sample(c: &mut Criterion) {
+    let mut group = c.benchmark_group("per_sample");
+    let synth = Arc::new(RwLock::new(SynthChannel::from(SynthEngineType::WaveTable)));
+
+    for key in CHORD {
+        synth.write().unwrap().engine.play(key, 100);
+    }
+
+    for size in BUFFER_SIZES {
+        let mut buf = vec![0.0f32; size];
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| {
+                for sample in buf.iter_mut() {
+                    *sample = synth
+                        .write()
+                        .map(|mut synth| synth.get_sample())
+                        .unwrap_or(0.0);
+                }
+
+                black_box(&buf);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block");
+    let (mut host, mut commands) = SynthHost::new(SynthEngineType::WaveTable);
+
+    for key in CHORD {
+        let _ = commands.push(SynthCmd::NoteOn { key, velocity: 100 }.into());
+    }
+
+    for size in BUFFER_SIZES {
+        let mut left = vec![0.0f32; size];
+        let mut right = vec![0.0f32; size];
+        host.prepare(size, SAMPLE_RATE as usize);
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| {
+                host.render_block(&mut left, &mut right);
+                black_box((&left, &right));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, per_sample, block);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! feeds arbitrary bytes to `MidiParser`, split into arbitrary chunks. the parser must never
+//! panic, and how the stream is split must not change what comes out of it.
+//!
+//! run with `cargo +nightly fuzz run midi_parser` from `core/`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use midi_control::MidiMessage;
+use synth_tab_core::midi::parser::MidiParser;
+
+fn parse<'a>(chunks: impl Iterator<Item = &'a [u8]>) -> Vec<String> {
+    let mut parser = MidiParser::new();
+    let mut messages = Vec::new();
+
+    for chunk in chunks {
+        parser.push(chunk, |msg: MidiMessage| messages.push(format!("{msg:?}")));
+    }
+
+    messages
+}
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte picks the chunk size, the rest is the stream.
+    let Some((&chunk, bytes)) = data.split_first() else {
+        return;
+    };
+    let chunk = chunk as usize % 16 + 1;
+
+    let whole = parse(std::iter::once(bytes));
+    let chunked = parse(bytes.chunks(chunk));
+    let bytewise = parse(bytes.chunks(1));
+
+    assert_eq!(whole, chunked);
+    assert_eq!(whole, bytewise);
+});
@@ -0,0 +1,87 @@
+use super::{AudioBackend, AudioError, AudioParams, DeviceThread, RenderFn};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, SampleRate, StreamConfig};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// desktop backend using the default output device of the default cpal host (ALSA, or JACK
+/// when it is enabled and running).
+#[derive(Debug, Default)]
+pub struct CpalBackend {
+    stream: Option<DeviceThread>,
+    /// callback to playback time from the last callback, in nanoseconds.
+    delay: Arc<AtomicU64>,
+    /// the last error cpal reported on the running stream.
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl AudioBackend for CpalBackend {
+    fn name(&self) -> &'static str {
+        "cpal"
+    }
+
+    fn start(&mut self, params: AudioParams, mut render: RenderFn) -> Result<(), AudioError> {
+        self.stop();
+
+        let delay = self.delay.clone();
+        delay.store(0, Ordering::Relaxed);
+        let error = self.error.clone();
+        self.take_error();
+
+        self.stream = Some(DeviceThread::spawn(move || {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or(AudioError::NoDevice)?;
+            let config = StreamConfig {
+                channels: params.channels as u16,
+                sample_rate: SampleRate(params.sample_rate as u32),
+                buffer_size: BufferSize::Fixed(params.buffer_size as u32),
+            };
+            let stream = device
+                .build_output_stream(
+                    &config,
+                    move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                        let stamp = info.timestamp();
+
+                        if let Some(ahead) = stamp.playback.duration_since(&stamp.callback) {
+                            delay.store(ahead.as_nanos() as u64, Ordering::Relaxed);
+                        }
+
+                        render(data)
+                    },
+                    move |e| {
+                        log::error!("cpal stream error: {e}");
+
+                        if let Ok(mut error) = error.lock() {
+                            error.replace(e.to_string());
+                        }
+                    },
+                    None,
+                )
+                .map_err(|e| AudioError::Device(e.to_string()))?;
+            stream
+                .play()
+                .map_err(|e| AudioError::Device(e.to_string()))?;
+
+            Ok(stream)
+        })?);
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stream = None;
+    }
+
+    fn output_delay(&self) -> Option<Duration> {
+        match self.delay.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    fn take_error(&mut self) -> Option<AudioError> {
+        self.error.lock().ok()?.take().map(AudioError::Stream)
+    }
+}
@@ -0,0 +1,99 @@
+//! output latency, both what the buffer settings promise and what the device actually does.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// how much each callback moves the smoothed callback period, out of 1.
+const PERIOD_SMOOTHING: f64 = 0.05;
+
+/// updated by the audio thread on every callback, read by the UI. lock free so the audio thread
+/// never waits on it.
+#[derive(Debug)]
+pub struct LatencyMeter {
+    start: Instant,
+    /// nanoseconds since `start` of the last callback, 0 before the first one.
+    last_callback: AtomicU64,
+    /// nanoseconds since `start` of the last `reset`.
+    reset_at: AtomicU64,
+    /// frames the device asked for in the last callback.
+    frames: AtomicUsize,
+    /// smoothed time between callbacks, as `f64` bits.
+    period: AtomicU64,
+}
+
+impl Default for LatencyMeter {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            last_callback: AtomicU64::new(0),
+            reset_at: AtomicU64::new(0),
+            frames: AtomicUsize::new(0),
+            period: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+}
+
+impl LatencyMeter {
+    /// called from the render callback with the number of frames it was asked for.
+    pub fn record(&self, frames: usize) {
+        let now = self.start.elapsed().as_nanos() as u64;
+        let last = self.last_callback.swap(now.max(1), Ordering::Relaxed);
+        self.frames.store(frames, Ordering::Relaxed);
+
+        if last == 0 {
+            return;
+        }
+
+        let interval = (now - last) as f64 * 1.0e-9;
+        let period = f64::from_bits(self.period.load(Ordering::Relaxed));
+        let period = if period == 0.0 {
+            interval
+        } else {
+            period + (interval - period) * PERIOD_SMOOTHING
+        };
+        self.period.store(period.to_bits(), Ordering::Relaxed);
+    }
+
+    /// forgets the old device's numbers, for when the output restarts.
+    pub fn reset(&self) {
+        let now = self.start.elapsed().as_nanos() as u64;
+        self.reset_at.store(now, Ordering::Relaxed);
+        self.last_callback.store(0, Ordering::Relaxed);
+        self.frames.store(0, Ordering::Relaxed);
+        self.period.store(0f64.to_bits(), Ordering::Relaxed);
+    }
+
+    /// time since the device last called back, or since the last `reset` if it hasn't yet.
+    pub fn since_callback(&self) -> Duration {
+        let now = self.start.elapsed().as_nanos() as u64;
+        let last = self
+            .last_callback
+            .load(Ordering::Relaxed)
+            .max(self.reset_at.load(Ordering::Relaxed));
+
+        Duration::from_nanos(now.saturating_sub(last))
+    }
+
+    /// frames per callback the device is really using, `None` until it has called back.
+    pub fn frames(&self) -> Option<usize> {
+        Some(self.frames.load(Ordering::Relaxed)).filter(|frames| *frames > 0)
+    }
+
+    /// smoothed time between callbacks, `None` until there have been two.
+    pub fn period(&self) -> Option<Duration> {
+        let period = f64::from_bits(self.period.load(Ordering::Relaxed));
+        (period > 0.0).then(|| Duration::from_secs_f64(period))
+    }
+}
+
+/// a snapshot for the UI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Latency {
+    /// one buffer at the requested size and rate.
+    pub theoretical: Duration,
+    /// one buffer at the size the device really asks for, plus whatever the driver says sits
+    /// between the callback and the speaker. `None` before the device has started.
+    pub measured: Option<Duration>,
+    /// how often the device really calls back.
+    pub callback_period: Option<Duration>,
+}
@@ -0,0 +1,237 @@
+//! audio output backends. `TabSynth` hands a backend a render callback and the backend decides
+//! where the samples go (a sound card, a WAV file, or nowhere).
+
+use crossbeam::channel::{bounded, Sender};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread::{spawn, JoinHandle};
+use std::time::Duration;
+use stepper_synth_backend::{CHANNEL_SIZE, SAMPLE_RATE};
+
+#[cfg(feature = "cpal")]
+pub mod cpal;
+pub mod latency;
+pub mod null;
+pub mod tinyaudio;
+pub mod wav;
+
+/// fills an interleaved buffer of `AudioParams::channels` channels.
+pub type RenderFn = Box<dyn FnMut(&mut [f32]) + Send + 'static>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioParams {
+    pub sample_rate: usize,
+    pub channels: usize,
+    /// frames per buffer.
+    pub buffer_size: usize,
+}
+
+/// sample rates offered in the settings screen.
+pub const SAMPLE_RATES: [usize; 4] = [22_050, 44_100, 48_000, 96_000];
+/// buffer sizes offered in the settings screen, in frames.
+pub const BUFFER_SIZES: [usize; 6] = [64, 128, 256, 512, 1024, 2048];
+
+impl AudioParams {
+    /// how long one buffer lasts at these settings.
+    pub fn buffer_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.buffer_size as f64 / self.sample_rate as f64)
+    }
+
+    /// these params followed by progressively safer ones to try if the device won't take them:
+    /// the default buffer size, then the default rate, then mono.
+    pub fn fallbacks(&self) -> Vec<AudioParams> {
+        let default = AudioParams::default();
+        let candidates = [
+            *self,
+            AudioParams {
+                buffer_size: default.buffer_size,
+                ..*self
+            },
+            default,
+            AudioParams {
+                channels: 1,
+                ..default
+            },
+        ];
+
+        let mut fallbacks = Vec::with_capacity(candidates.len());
+
+        for params in candidates {
+            if !fallbacks.contains(&params) {
+                fallbacks.push(params);
+            }
+        }
+
+        fallbacks
+    }
+}
+
+impl Default for AudioParams {
+    fn default() -> Self {
+        Self {
+            sample_rate: SAMPLE_RATE as usize,
+            channels: 2,
+            buffer_size: CHANNEL_SIZE,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AudioError {
+    /// there is no output device to open.
+    NoDevice,
+    /// the driver refused to open or run the output device.
+    Device(String),
+    /// the driver reported an error on a stream that was already running, e.g. the device was
+    /// unplugged.
+    Stream(String),
+    /// the device stopped asking for audio without saying why.
+    Stalled(Duration),
+    Io(std::io::Error),
+    Wav(hound::Error),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDevice => write!(f, "no audio output device"),
+            Self::Device(e) => write!(f, "audio device error: {e}"),
+            Self::Stream(e) => write!(f, "audio stream failed: {e}"),
+            Self::Stalled(d) => write!(f, "audio device stopped for {} ms", d.as_millis()),
+            Self::Io(e) => write!(f, "audio io error: {e}"),
+            Self::Wav(e) => write!(f, "could not write WAV file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+impl From<std::io::Error> for AudioError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<hound::Error> for AudioError {
+    fn from(e: hound::Error) -> Self {
+        Self::Wav(e)
+    }
+}
+
+pub trait AudioBackend: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// starts pulling audio from `render` until `stop` is called or the backend is dropped.
+    fn start(&mut self, params: AudioParams, render: RenderFn) -> Result<(), AudioError>;
+
+    fn stop(&mut self);
+
+    /// time between a callback and its audio reaching the speaker, for drivers that report it.
+    fn output_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// an error the driver reported while running, if there was one since the last call.
+    fn take_error(&mut self) -> Option<AudioError> {
+        None
+    }
+}
+
+/// which backend to start with, picked by the platform at start up.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BackendConfig {
+    #[default]
+    TinyAudio,
+    #[cfg(feature = "cpal")]
+    Cpal,
+    Null,
+    Wav(PathBuf),
+}
+
+impl BackendConfig {
+    pub fn build(&self) -> Box<dyn AudioBackend> {
+        match self {
+            Self::TinyAudio => Box::new(tinyaudio::TinyAudioBackend::default()),
+            #[cfg(feature = "cpal")]
+            Self::Cpal => Box::new(cpal::CpalBackend::default()),
+            Self::Null => Box::new(null::NullBackend::default()),
+            Self::Wav(path) => Box::new(wav::WavBackend::new(path.clone())),
+        }
+    }
+}
+
+impl FromStr for BackendConfig {
+    type Err = String;
+
+    /// parses `tinyaudio`, `cpal`, `null` or `wav:<path>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tinyaudio" => Ok(Self::TinyAudio),
+            #[cfg(feature = "cpal")]
+            "cpal" => Ok(Self::Cpal),
+            "null" => Ok(Self::Null),
+            _ => match s.strip_prefix("wav:") {
+                Some(path) if !path.is_empty() => Ok(Self::Wav(PathBuf::from(path))),
+                _ => Err(format!("unknown audio backend {s:?}")),
+            },
+        }
+    }
+}
+
+/// keeps a driver handle alive on its own thread. some drivers hand back handles that are not
+/// `Send`, so they are made, held, and dropped on the same thread.
+#[derive(Debug)]
+pub(crate) struct DeviceThread {
+    stop: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DeviceThread {
+    pub(crate) fn spawn<T, F>(make: F) -> Result<Self, AudioError>
+    where
+        F: FnOnce() -> Result<T, AudioError> + Send + 'static,
+        T: 'static,
+    {
+        let (started_tx, started_rx) = bounded(1);
+        let (stop, stop_rx) = bounded::<()>(1);
+
+        let handle = spawn(move || {
+            let device = match make() {
+                Ok(device) => {
+                    let _ = started_tx.send(Ok(()));
+                    device
+                }
+                Err(e) => {
+                    let _ = started_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            // blocks until `stop` is sent or dropped.
+            let _ = stop_rx.recv();
+            drop(device);
+        });
+
+        match started_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                stop,
+                handle: Some(handle),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(AudioError::Device(
+                "audio thread exited before the device started".into(),
+            )),
+        }
+    }
+}
+
+impl Drop for DeviceThread {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use super::{AudioBackend, AudioError, AudioParams, RenderFn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{sleep, spawn, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// pulls buffers at the pace a sound card would and throws them away. keeps the synth running
+/// (and timing realistic) on machines with no audio hardware, like CI runners.
+#[derive(Debug, Default)]
+pub struct NullBackend {
+    exit: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// renders buffers in real time on a new thread until `exit` is set, handing each one to `sink`.
+pub(crate) fn spawn_paced<S>(
+    params: AudioParams,
+    mut render: RenderFn,
+    exit: Arc<AtomicBool>,
+    mut sink: S,
+) -> JoinHandle<()>
+where
+    S: FnMut(&[f32]) + Send + 'static,
+{
+    spawn(move || {
+        let mut buf = vec![0.0; params.buffer_size * params.channels];
+        let period = Duration::from_secs_f64(params.buffer_size as f64 / params.sample_rate as f64);
+        let mut next = Instant::now();
+
+        while !exit.load(Ordering::Relaxed) {
+            render(&mut buf);
+            sink(&buf);
+
+            next += period;
+
+            if let Some(wait) = next.checked_duration_since(Instant::now()) {
+                sleep(wait);
+            }
+        }
+    })
+}
+
+impl AudioBackend for NullBackend {
+    fn name(&self) -> &'static str {
+        "null"
+    }
+
+    fn start(&mut self, params: AudioParams, render: RenderFn) -> Result<(), AudioError> {
+        self.stop();
+        self.exit = Arc::new(AtomicBool::new(false));
+        self.handle = Some(spawn_paced(params, render, self.exit.clone(), |_| {}));
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.exit.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for NullBackend {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,34 @@
+use super::{AudioBackend, AudioError, AudioParams, DeviceThread, RenderFn};
+use tinyaudio::{run_output_device, OutputDeviceParameters};
+
+/// the default backend, AAudio on android and ALSA on linux.
+#[derive(Debug, Default)]
+pub struct TinyAudioBackend {
+    device: Option<DeviceThread>,
+}
+
+impl AudioBackend for TinyAudioBackend {
+    fn name(&self) -> &'static str {
+        "tinyaudio"
+    }
+
+    fn start(&mut self, params: AudioParams, render: RenderFn) -> Result<(), AudioError> {
+        self.stop();
+
+        let params = OutputDeviceParameters {
+            channels_count: params.channels,
+            sample_rate: params.sample_rate,
+            channel_sample_count: params.buffer_size,
+        };
+
+        self.device = Some(DeviceThread::spawn(move || {
+            run_output_device(params, render).map_err(|e| AudioError::Device(e.to_string()))
+        })?);
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.device = None;
+    }
+}
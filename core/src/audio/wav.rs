@@ -0,0 +1,73 @@
+use super::null::spawn_paced;
+use super::{AudioBackend, AudioError, AudioParams, RenderFn};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// records everything the synth plays to a 32-bit float WAV file, in real time. the file is
+/// finalized when the backend is stopped.
+#[derive(Debug)]
+pub struct WavBackend {
+    path: PathBuf,
+    exit: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WavBackend {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            exit: Arc::new(AtomicBool::new(false)),
+            handle: None,
+        }
+    }
+}
+
+impl AudioBackend for WavBackend {
+    fn name(&self) -> &'static str {
+        "wav"
+    }
+
+    fn start(&mut self, params: AudioParams, render: RenderFn) -> Result<(), AudioError> {
+        self.stop();
+
+        let spec = hound::WavSpec {
+            channels: params.channels as u16,
+            sample_rate: params.sample_rate as u32,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = Some(hound::WavWriter::create(&self.path, spec)?);
+        let path = self.path.clone();
+
+        self.exit = Arc::new(AtomicBool::new(false));
+        self.handle = Some(spawn_paced(params, render, self.exit.clone(), move |buf| {
+            let Some(w) = writer.as_mut() else {
+                return;
+            };
+
+            if let Err(e) = buf.iter().try_for_each(|sample| w.write_sample(*sample)) {
+                log::error!("writing {path:?} failed, recording stopped: {e}");
+                writer = None;
+            }
+        }));
+
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        // the writer lives in the render thread and finalizes itself when dropped.
+        self.exit.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for WavBackend {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,174 @@
+//! user settings that outlive the app, kept as TOML in the directory the platform hands us
+//! (`Platform::data_dir`). a missing or broken file just means defaults.
+
+use crate::audio::AudioParams;
+use crate::midi::channel::ChannelFilter;
+use crate::midi::learn::MidiMap;
+use crate::midi::mpe::MpeConfig;
+use crate::midi::output::OutputConfig;
+use crate::midi::zones::ZoneConfig;
+use crate::synth::{BendRange, MultiConfig};
+use crate::transport::TransportConfig;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+lazy_static! {
+    /// the settings in use, loaded by `run` before anything reads them.
+    pub static ref CONFIG: RwLock<Config> = RwLock::new(Config::default());
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Encode(toml::ser::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not write config: {e}"),
+            Self::Encode(e) => write!(f, "could not encode config: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(e: toml::ser::Error) -> Self {
+        Self::Encode(e)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub audio: AudioConfig,
+    /// the synth's receive channel and per-device channel filters.
+    pub midi: ChannelFilter,
+    pub bend: BendConfig,
+    /// CC bindings, made by MIDI learn or on the mapping screen.
+    pub mappings: MidiMap,
+    pub midi_out: OutputConfig,
+    pub mpe: MpeConfig,
+    /// multi-timbral mode and its parts.
+    pub multi: MultiConfig,
+    /// keyboard splits and layers.
+    pub zones: ZoneConfig,
+    /// tempo, and where it comes from.
+    pub transport: TransportConfig,
+    /// where this was loaded from and gets saved to, `None` when the platform has nowhere to
+    /// keep files.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub sample_rate: usize,
+    /// frames per buffer.
+    pub buffer_size: usize,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        let params = AudioParams::default();
+
+        Self {
+            sample_rate: params.sample_rate,
+            buffer_size: params.buffer_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BendConfig {
+    /// the range the synth starts with. RPN 0 changes it until the next restart.
+    pub range: BendRange,
+    /// wheel movement around center that counts as no bend, out of 1.
+    pub deadzone: f32,
+}
+
+impl Default for BendConfig {
+    fn default() -> Self {
+        Self {
+            range: BendRange::default(),
+            deadzone: 0.02,
+        }
+    }
+}
+
+impl AudioConfig {
+    pub fn params(&self) -> AudioParams {
+        AudioParams {
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size,
+            ..AudioParams::default()
+        }
+    }
+}
+
+impl Config {
+    /// reads `<dir>/config.toml`, falling back to the defaults (with a warning) if it can't.
+    pub fn load(dir: Option<&Path>) -> Self {
+        let Some(path) = dir.map(|dir| dir.join(CONFIG_FILE)) else {
+            return Self::default();
+        };
+
+        let mut config = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+                log::warn!("{} is not valid, using defaults: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::warn!("could not read {}, using defaults: {e}", path.display());
+                Self::default()
+            }
+        };
+
+        config.path = Some(path);
+        config
+    }
+
+    /// writes the config back to where it was loaded from. does nothing without a path.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// changes the config and writes it out.
+pub fn save_config(change: impl FnOnce(&mut Config)) {
+    let Ok(mut config) = CONFIG.write() else {
+        return;
+    };
+
+    change(&mut config);
+
+    if let Err(e) = config.save() {
+        log::error!("{e}");
+    }
+}
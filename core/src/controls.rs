@@ -0,0 +1,1683 @@
+use iced_core::Element;
+use iced_wgpu::Renderer;
+use iced_widget::{
+    button, checkbox, column, container, horizontal_space, mouse_area, pick_list, row, scrollable,
+    slider, text, text_editor, text_input, vertical_space, PickList, Row, Slider, Space,
+};
+use iced_winit::core::{Alignment, Border, Color, Font, Length, Theme};
+use iced_winit::runtime::{Program, Task};
+use iced_winit::winit::event_loop::EventLoopProxy;
+use log::*;
+use midi_control::{ControlEvent, KeyEvent, MidiMessage};
+use std::collections::BTreeSet;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use stepper_synth_backend::pygame_coms::{SynthEngineType, WTSynthParam};
+use stepper_synth_backend::synth_engines::wave_table::WaveTableEngine;
+use stepper_synth_backend::synth_engines::SynthModule;
+
+use crate::audio::{AudioParams, BUFFER_SIZES, SAMPLE_RATES};
+use crate::config::{save_config, CONFIG};
+use crate::midi::channel::{ChannelFilter, ReceiveChannel};
+use crate::midi::devices;
+use crate::midi::learn::{self, Binding, Curve, Target};
+use crate::midi::monitor::{self, Entry, Kind, Logged};
+use crate::midi::mpe::{self, MpeConfig};
+use crate::midi::output;
+use crate::midi::source::KNOWN_SOURCES;
+use crate::midi::zones::{Zone, ZoneConfig, MAX_TRANSPOSE};
+use crate::midi::{self, SystemMessage, CHANNEL_MODE_CC};
+use crate::patches::{self, Patch};
+use crate::synth::{
+    bend, AudioStatus, BendRange, MultiConfig, PartConfig, PartEngine, StereoParam, SynthCmd,
+    TabSynth, MAX_PARTS,
+};
+use crate::transport::{self, ClockSource, TransportConfig};
+use crate::UserEvent;
+
+// const EXAMPLES: [Example; 3] = [Example::Integration, Example::Counter, Example::TextEditor];
+
+// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// pub enum Example {
+//     Integration,
+//     Counter,
+//     TextEditor,
+// }
+
+// impl std::fmt::Display for Example {
+//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//         write!(f, "{:?}", self)
+//     }
+// }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SynthScreen {
+    Osc,
+    Env,
+    LFO,
+    LowPass,
+    ModMatrix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Screen {
+    Settings,
+    MidiSelection,
+    Mappings,
+    Monitor,
+    Parts,
+    Zones,
+    SynthScreen(SynthScreen),
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    OpenSettingsMenu,
+    OpenMidiMenu,
+    SwitchSynthScreen(SynthScreen),
+    SetSynthParam {
+        param: WTSynthParam,
+    },
+    SetStereo(StereoParam),
+    SetSampleRate(usize),
+    SetBufferSize(usize),
+    ApplyAudioSettings,
+    RetryAudio,
+    SetReceiveChannel(ReceiveChannel),
+    SetDeviceChannel(String, ReceiveChannel),
+    SetBendRange(BendRange),
+    SetThru(bool),
+    SetSendLocalNotes(bool),
+    SetSendParams(bool),
+    SetOutChannel(u8),
+    /// a key on the on-screen keyboard was pressed or let go.
+    KeyDown(u8),
+    KeyUp(u8),
+    /// moves the on-screen keyboard by this many octaves.
+    ShiftOctave(i8),
+    /// doesn't save, `SaveConfig` follows on release.
+    SetBendDeadzone(f32),
+    OpenMappings,
+    /// while on, touching a parameter waits for a controller to bind to it.
+    ToggleLearn,
+    Learn(Target),
+    CancelLearn,
+    /// replaces the binding at an index in the mapping table and saves it.
+    SetBinding(usize, Binding),
+    /// like `SetBinding` but doesn't save, for sliders. `SaveConfig` follows on release.
+    DragBinding(usize, Binding),
+    /// writes out the config, sent when a slider that changed it is let go.
+    SaveConfig,
+    RemoveBinding(usize),
+    SetDeviceEnabled(String, bool),
+    SetMpe(MpeConfig),
+    OpenParts,
+    SetMulti(bool),
+    AddPart,
+    RemovePart(usize),
+    /// picks the part the synth screens edit and the on-screen keyboard plays.
+    SelectPart(usize),
+    SetPart(usize, PartConfig),
+    /// stores how a part sounds now as the patch at its bank and program.
+    StorePatch(usize),
+    /// like `SetPart` but doesn't save, for the level slider. `SaveConfig` follows on release.
+    DragPart(usize, PartConfig),
+    OpenZones,
+    SetZonesEnabled(bool),
+    AddZone,
+    RemoveZone(usize),
+    SetZone(usize, Zone),
+    /// starts the internal clock from the top, or stops it.
+    TogglePlay,
+    Tap,
+    SetTransport(TransportConfig),
+    /// like `SetTransport` but doesn't save, for the tempo slider. `SaveConfig` follows on
+    /// release.
+    DragTransport(TransportConfig),
+    OpenMonitor,
+    /// stops or restarts logging in the MIDI monitor.
+    ToggleMonitorPause,
+    ClearMonitor,
+    SetMonitorFilter(monitor::Filter),
+    /// a MIDI device came or went, does nothing itself.
+    DevicesChanged,
+    /// sent once a second so readouts refresh, does nothing itself.
+    Tick,
+}
+
+#[derive(Debug)]
+pub struct Controls {
+    background_color: Color,
+    input: String,
+    value: i32,
+    screen: Screen,
+    editor: text_editor::Content<Renderer>,
+    proxy: EventLoopProxy<UserEvent>,
+    synth: Arc<RwLock<TabSynth>>,
+    /// audio settings picked in the settings screen, not applied until "Apply" is pressed.
+    audio: AudioParams,
+    /// why the last "Apply" failed.
+    audio_error: Option<String>,
+    /// MIDI learn mode, see `Message::ToggleLearn`.
+    learn_mode: bool,
+    /// the note of the on-screen keyboard's lowest key, always a C.
+    keyboard_base: u8,
+    /// on-screen keys being held, so each gets exactly one note off.
+    keys_down: BTreeSet<u8>,
+    /// what the MIDI monitor shows.
+    monitor_filter: monitor::Filter,
+}
+
+// #[derive(Debug, Clone)]
+// pub enum Message {
+//     RedChanged(f32),
+//     GreenChanged(f32),
+//     BlueChanged(f32),
+//     InputChanged(String),
+//     EditorAction(text_editor::Action),
+//     ExampleSelected(Example),
+//     Inc,
+//     Dec,
+// }
+
+impl Controls {
+    pub fn new(proxy: EventLoopProxy<UserEvent>, synth: Arc<RwLock<TabSynth>>) -> Controls {
+        Controls {
+            background_color: Color::BLACK,
+            input: String::default(),
+            value: 0,
+            screen: Screen::SynthScreen(SynthScreen::Osc),
+            editor: text_editor::Content::new(),
+            proxy,
+            audio: synth.read().unwrap().audio_params(),
+            audio_error: None,
+            learn_mode: false,
+            keyboard_base: 48,
+            keys_down: BTreeSet::new(),
+            monitor_filter: monitor::Filter::default(),
+            synth,
+        }
+    }
+
+    pub fn background_color(&self) -> Color {
+        self.background_color
+    }
+}
+
+impl Program for Controls {
+    type Theme = Theme;
+    type Message = Message;
+    type Renderer = Renderer;
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            // Message::Inc => self.value += 1,
+            // Message::Dec => self.value -= 1,
+            // Message::ExampleSelected(example) => self.selected_example = example,
+            // Message::InputChanged(value) => self.input = value,
+            // Message::RedChanged(r) => self.background_color.r = r,
+            // Message::GreenChanged(g) => self.background_color.g = g,
+            // Message::BlueChanged(b) => self.background_color.b = b,
+            // Message::EditorAction(action) => match action {
+            //     text_editor::Action::Focus => {
+            //         log::info!("Editor focused");
+            //         // it's possible to call java::call_instance_method("showKeyboard")
+            //         // right here, but needed something to show the usage of user events
+            //         let _ = self.proxy.send_event(UserEvent::ShowKeyboard);
+            //     }
+            //     text_editor::Action::Blur => {
+            //         log::info!("Editor lost focus");
+            //         let _ = self.proxy.send_event(UserEvent::HideKeyboard);
+            //     }
+            //     other => self.editor.perform(other),
+            // },
+            Message::OpenSettingsMenu => {
+                self.audio = self.synth.read().unwrap().audio_params();
+                self.screen = Screen::Settings;
+            }
+            Message::OpenMidiMenu => self.screen = Screen::MidiSelection,
+            Message::SetSynthParam { param } => match param {
+                _ => warn!("Settings wavetable synth params not written yet"),
+            },
+            Message::SetStereo(param) => {
+                if let Some((target, value)) = Target::from_stereo(param) {
+                    if self.learn_mode {
+                        learn::learn(target);
+                    }
+
+                    output::local_param(target, value);
+                }
+
+                if let Ok(mut synth) = self.synth.write() {
+                    synth.set_stereo(param);
+                }
+            }
+            Message::SetSampleRate(rate) => self.audio.sample_rate = rate,
+            Message::SetBufferSize(size) => self.audio.buffer_size = size,
+            Message::ApplyAudioSettings => self.apply_audio_settings(),
+            Message::SetReceiveChannel(channel) => {
+                save_config(|config| config.midi.receive_channel = channel)
+            }
+            Message::SetDeviceChannel(device, channel) => {
+                save_config(|config| config.midi.set_device(&device, channel))
+            }
+            Message::SetDeviceEnabled(device, enabled) => {
+                save_config(|config| config.midi.set_enabled(&device, enabled))
+            }
+            Message::SetMpe(mpe) => self.set_mpe(mpe),
+            Message::OpenParts => self.screen = Screen::Parts,
+            Message::SetMulti(enabled) => self.set_parts(|multi| multi.enabled = enabled),
+            Message::AddPart => self.set_parts(|multi| {
+                multi.add_part();
+            }),
+            Message::RemovePart(index) => {
+                save_config(|config| config.multi.remove_part(index));
+                let config = CONFIG.read().unwrap().clone();
+
+                // the parts after it moved up a slot.
+                if let Ok(mut synth) = self.synth.write() {
+                    synth.reload_parts(index, &config);
+                }
+            }
+            Message::SelectPart(index) => {
+                if let Ok(mut synth) = self.synth.write() {
+                    synth.select_part(index);
+                }
+            }
+            Message::SetPart(index, part) => self.set_part(index, part),
+            Message::StorePatch(index) => self.store_patch(index),
+            Message::DragPart(index, part) => {
+                if let Ok(mut config) = CONFIG.write() {
+                    if let Some(p) = config.multi.parts.get_mut(index) {
+                        *p = part;
+                    }
+                }
+
+                if let Ok(mut synth) = self.synth.write() {
+                    synth.set_level(index, part.level);
+                }
+            }
+            Message::OpenZones => self.screen = Screen::Zones,
+            Message::SetZonesEnabled(enabled) => self.set_zones(|zones| zones.enabled = enabled),
+            Message::AddZone => self.set_zones(|zones| zones.list.push(Zone::default())),
+            Message::RemoveZone(index) => self.set_zones(|zones| {
+                if index < zones.list.len() {
+                    zones.list.remove(index);
+                }
+            }),
+            Message::SetZone(index, zone) => self.set_zones(|zones| {
+                if let Some(z) = zones.list.get_mut(index) {
+                    *z = zone;
+                }
+            }),
+            Message::TogglePlay => match transport::playing() {
+                true => transport::stop(),
+                false => transport::start(true),
+            },
+            Message::Tap => {
+                if let Some(bpm) = transport::tap(Instant::now()) {
+                    save_config(|config| config.transport.bpm = bpm);
+                }
+            }
+            Message::SetTransport(config) => {
+                transport::configure(config);
+                save_config(|c| c.transport = config);
+            }
+            Message::DragTransport(config) => {
+                transport::configure(config);
+
+                if let Ok(mut c) = CONFIG.write() {
+                    c.transport = config;
+                }
+            }
+            Message::SetBendRange(range) => {
+                if let Ok(mut synth) = self.synth.write() {
+                    synth.set_bend_range(range);
+                }
+
+                save_config(|config| config.bend.range = range);
+            }
+            Message::SetBendDeadzone(deadzone) => {
+                if let Ok(mut config) = CONFIG.write() {
+                    config.bend.deadzone = deadzone;
+                }
+            }
+            Message::SetThru(thru) => save_config(|config| config.midi_out.thru = thru),
+            Message::SetSendLocalNotes(on) => {
+                save_config(|config| config.midi_out.local_notes = on)
+            }
+            Message::SetSendParams(on) => save_config(|config| config.midi_out.params = on),
+            Message::SetOutChannel(channel) => {
+                save_config(|config| config.midi_out.channel = channel)
+            }
+            Message::KeyDown(key) => self.key_down(key),
+            Message::KeyUp(key) => self.key_up(key),
+            Message::ShiftOctave(octaves) => {
+                for key in std::mem::take(&mut self.keys_down) {
+                    self.keys_down.insert(key);
+                    self.key_up(key);
+                }
+
+                let base = self.keyboard_base as i16 + octaves as i16 * 12;
+                self.keyboard_base = base.clamp(0, 108) as u8;
+            }
+            Message::OpenMappings => self.screen = Screen::Mappings,
+            Message::OpenMonitor => self.screen = Screen::Monitor,
+            Message::ToggleMonitorPause => monitor::set_paused(!monitor::is_paused()),
+            Message::ClearMonitor => monitor::clear(),
+            Message::SetMonitorFilter(filter) => self.monitor_filter = filter,
+            Message::ToggleLearn => {
+                self.learn_mode = !self.learn_mode;
+
+                if !self.learn_mode {
+                    learn::cancel_learn();
+                }
+            }
+            Message::Learn(target) => learn::learn(target),
+            Message::CancelLearn => learn::cancel_learn(),
+            Message::SetBinding(i, binding) => save_config(|config| {
+                if let Some(b) = config.mappings.bindings.get_mut(i) {
+                    *b = binding;
+                }
+            }),
+            Message::DragBinding(i, binding) => {
+                if let Ok(mut config) = CONFIG.write() {
+                    if let Some(b) = config.mappings.bindings.get_mut(i) {
+                        *b = binding;
+                    }
+                }
+            }
+            Message::SaveConfig => save_config(|_| {}),
+            Message::RemoveBinding(i) => save_config(|config| {
+                if i < config.mappings.bindings.len() {
+                    config.mappings.bindings.remove(i);
+                }
+            }),
+            Message::RetryAudio => {
+                if let Ok(mut synth) = self.synth.write() {
+                    synth.retry_audio();
+                }
+            }
+            Message::DevicesChanged | Message::Tick => {}
+            Message::SwitchSynthScreen(screen) => self.screen = Screen::SynthScreen(screen),
+        }
+
+        // the monitor only asks for redraws while it can be seen.
+        monitor::watch(self.screen == Screen::Monitor);
+
+        Task::none()
+    }
+
+    fn view(&self) -> Element<Message, Theme, Renderer> {
+        let top_bar = row![
+            // settings button
+            container(
+                button("Settings").on_press(Message::OpenSettingsMenu) // .alig(Alignment::Left)
+                                                                       // .into()
+            )
+            .align_x(Alignment::Start),
+            container(row![
+                // Oscilator button
+                button("Oscilator").on_press(Message::SwitchSynthScreen(SynthScreen::Osc)),
+                // .alig(Alignment::Left)knob_widge
+                // .into(),
+                // .center
+                // adsr button
+                button("ADSR").on_press(Message::SwitchSynthScreen(SynthScreen::Env)),
+                // LFO button
+                button("LFO").on_press(Message::SwitchSynthScreen(SynthScreen::LFO)),
+                // Lowpass Filter button
+                button("Low-Pass").on_press(Message::SwitchSynthScreen(SynthScreen::LowPass)),
+                // Mod Matrix button
+                button("Mod-Matrix").on_press(Message::SwitchSynthScreen(SynthScreen::ModMatrix)),
+            ])
+            .width(Length::Fill)
+            .align_x(Alignment::Center),
+            // Midi Settings menu
+            container(row![
+                button("Zones").on_press(Message::OpenZones),
+                button("Parts").on_press(Message::OpenParts),
+                button("MIDI").on_press(Message::OpenMidiMenu), // .alig(Alignment::Left)
+                                                                // .into()
+            ])
+            .align_x(Alignment::End),
+        ]
+        // .spacing(Length::Fill)
+        .width(Length::Fill);
+
+        // match self.selected_example {
+        //     Example::Integration => self.integration(),
+        //     Example::Counter => self.counter(),
+        //     Example::TextEditor => self.text_editor(),
+        // }
+
+        // only the wavetable engine has editor screens so far.
+        let wavetable = matches!(
+            self.synth.read().unwrap().engine_type(),
+            SynthEngineType::WaveTable
+        );
+
+        let synth_screen = match self.screen {
+            Screen::Settings => self.settings(),
+            Screen::MidiSelection => self.midi_selection(),
+            Screen::Mappings => self.mappings(),
+            Screen::Monitor => self.monitor(),
+            Screen::Parts => self.parts(),
+            Screen::Zones => self.zones(),
+            Screen::SynthScreen(_) if !wavetable => row![text("no editor for this engine")
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center()]
+            .into(),
+            Screen::SynthScreen(SynthScreen::Osc) => self.osc(),
+            Screen::SynthScreen(SynthScreen::Env) => row![text("Env")
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center()]
+            .into(),
+            Screen::SynthScreen(SynthScreen::LFO) => row![text("LFO")
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center()]
+            .into(),
+            Screen::SynthScreen(SynthScreen::LowPass) => row![text("LowPass")
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center()]
+            .into(),
+            Screen::SynthScreen(SynthScreen::ModMatrix) => row![text("Mod")
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center()]
+            .into(),
+        };
+
+        let mut page = column![top_bar, self.transport_bar()];
+
+        if let Some(banner) = self.audio_banner() {
+            page = page.push(banner);
+        }
+
+        page = page.push(synth_screen);
+
+        if matches!(self.screen, Screen::SynthScreen(_)) {
+            page = page.push(self.stereo());
+            page = page.push(self.keyboard());
+
+            if let Some(target) = learn::learning().filter(|_| self.learn_mode) {
+                page = page.push(
+                    text(format!("move a controller to bind it to {target}")).width(Length::Fill),
+                );
+            }
+        }
+
+        page.width(Length::Fill).height(Length::Fill).into()
+
+        // top_bar.into()
+        // self.integration()
+    }
+}
+
+/// CC numbers a binding can listen to, everything below the channel mode messages.
+const BINDABLE_CCS: [u8; CHANNEL_MODE_CC as usize] = {
+    let mut all = [0; CHANNEL_MODE_CC as usize];
+    let mut i = 0;
+
+    while i < all.len() {
+        all[i] = i as u8;
+        i += 1;
+    }
+
+    all
+};
+
+/// velocity of notes from the on-screen keyboard.
+const LOCAL_VELOCITY: u8 = 100;
+/// keys on the on-screen keyboard, an octave and the C above it.
+const KEYBOARD_KEYS: u8 = 13;
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+/// member channel counts for an MPE zone, 0 is no zone.
+const MPE_MEMBERS: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+/// member channel bend ranges on offer, 48 is the MPE default.
+const MEMBER_BEND_SEMITONES: [u8; 5] = [12, 24, 36, 48, 96];
+/// knobs timbre can drive, 0 is none.
+const TIMBRE_KNOBS: [u8; 9] = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+/// MIDI channels, for channel pick lists.
+const CHANNELS: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+/// a key by name, for the zone pick lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Key(u8);
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = NOTE_NAMES[self.0 as usize % 12];
+        write!(f, "{name}{}", self.0 as i16 / 12 - 1)
+    }
+}
+
+/// a kind of message to show in the monitor, `None` for all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KindFilter(Option<Kind>);
+
+impl std::fmt::Display for KindFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(kind) => write!(f, "{kind}"),
+            None => write!(f, "Everything"),
+        }
+    }
+}
+
+const KIND_FILTERS: [KindFilter; 6] = [
+    KindFilter(None),
+    KindFilter(Some(Kind::Notes)),
+    KindFilter(Some(Kind::Controllers)),
+    KindFilter(Some(Kind::Programs)),
+    KindFilter(Some(Kind::Expression)),
+    KindFilter(Some(Kind::System)),
+];
+
+/// how many messages the monitor lists at once, newest first.
+const MONITOR_ROWS: usize = 200;
+
+/// a part by the number the parts screen shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PartNumber(usize);
+
+impl std::fmt::Display for PartNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Part {}", self.0 + 1)
+    }
+}
+
+const KEYS: [Key; 128] = {
+    let mut all = [Key(0); 128];
+    let mut i = 0;
+
+    while i < all.len() {
+        all[i] = Key(i as u8);
+        i += 1;
+    }
+
+    all
+};
+
+/// note on velocities, 0 is a note off.
+const VELOCITIES: [u8; 127] = {
+    let mut all = [0; 127];
+    let mut i = 0;
+
+    while i < all.len() {
+        all[i] = i as u8 + 1;
+        i += 1;
+    }
+
+    all
+};
+
+const TRANSPOSES: [i8; MAX_TRANSPOSE as usize * 2 + 1] = {
+    let mut all = [0; MAX_TRANSPOSE as usize * 2 + 1];
+    let mut i = 0;
+
+    while i < all.len() {
+        all[i] = i as i8 - MAX_TRANSPOSE;
+        i += 1;
+    }
+
+    all
+};
+
+/// program numbers, for the parts screen.
+const PROGRAMS: [u8; 128] = {
+    let mut all = [0; 128];
+    let mut i = 0;
+
+    while i < all.len() {
+        all[i] = i as u8;
+        i += 1;
+    }
+
+    all
+};
+
+/// banks on offer in the parts screen. bank select can reach the rest.
+const BANKS: [u16; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+/// bend ranges on offer in the MIDI screen. RPN 0 can go further.
+const BEND_SEMITONES: [u8; 25] = {
+    let mut all = [0; 25];
+    let mut i = 0;
+
+    while i < all.len() {
+        all[i] = i as u8;
+        i += 1;
+    }
+
+    all
+};
+
+/// a logged message in words, for the monitor.
+fn describe(message: &Logged) -> String {
+    match message {
+        Logged::Midi(msg) => match *msg {
+            MidiMessage::NoteOn(_, KeyEvent { key, value: 0 }) => {
+                format!("Note On {} (off)", Key(key))
+            }
+            MidiMessage::NoteOn(_, KeyEvent { key, value }) => {
+                format!("Note On {} vel {value}", Key(key))
+            }
+            MidiMessage::NoteOff(_, KeyEvent { key, value }) => {
+                format!("Note Off {} vel {value}", Key(key))
+            }
+            MidiMessage::PolyKeyPressure(_, KeyEvent { key, value }) => {
+                format!("Poly Pressure {} {value}", Key(key))
+            }
+            MidiMessage::ControlChange(_, ControlEvent { control, value }) => {
+                format!("CC {control} = {value}")
+            }
+            MidiMessage::ProgramChange(_, program) => format!("Program {program}"),
+            MidiMessage::ChannelPressure(_, value) => format!("Pressure {value}"),
+            MidiMessage::PitchBend(_, lsb, msb) => {
+                format!("Bend {:+.3}", bend::decode(lsb, msb))
+            }
+            ref other => format!("{other:?}"),
+        },
+        Logged::System(SystemMessage::SongPosition(position)) => {
+            format!("Song Position {position}")
+        }
+        Logged::System(msg) => format!("{msg:?}"),
+    }
+}
+
+/// a row of the monitor: when, from where, on which channel, what, and the bytes.
+fn monitor_row<'a>(entry: &Entry) -> Row<'a, Message, Theme, Renderer> {
+    let channel = entry
+        .channel()
+        .map_or_else(|| "-".to_string(), |channel| channel.to_string());
+    let bytes = entry
+        .bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let color = if entry.played {
+        Color::WHITE
+    } else {
+        // dropped by a device switch or channel filter.
+        Color::from_rgb(0.5, 0.5, 0.5)
+    };
+
+    let cell = |content: String| text(content).font(Font::MONOSPACE).color(color);
+
+    row![
+        cell(format!("{:.3}", entry.seconds())).width(100),
+        cell(entry.source.to_string()).width(200),
+        cell(channel).width(40),
+        cell(describe(&entry.message)).width(Length::Fill),
+        cell(bytes).width(120),
+    ]
+    .spacing(10)
+}
+
+/// what the parts screen shows for the patch a part is on.
+fn patch_name(patch: Option<Patch>) -> String {
+    match patch {
+        Some(patch) if patch.name.is_empty() => "(unnamed)".to_string(),
+        Some(patch) => patch.name,
+        None => "(no patch)".to_string(),
+    }
+}
+
+fn color_slider<'a>(value: f32, f: impl Fn(f32) -> Message + 'a) -> Slider<'a, f32, Message> {
+    slider(0.0..=1.0, value, f).step(0.01)
+}
+
+impl Controls {
+    /// plays `key` on the synth and sends it out.
+    fn key_down(&mut self, key: u8) {
+        if !self.keys_down.insert(key) {
+            return;
+        }
+
+        if let Ok(synth) = self.synth.read() {
+            synth.send(SynthCmd::NoteOn {
+                key,
+                velocity: LOCAL_VELOCITY,
+            });
+        }
+
+        output::local_note(key, LOCAL_VELOCITY);
+    }
+
+    fn key_up(&mut self, key: u8) {
+        if !self.keys_down.remove(&key) {
+            return;
+        }
+
+        if let Ok(synth) = self.synth.read() {
+            synth.send(SynthCmd::NoteOff { key });
+        }
+
+        output::local_note(key, 0);
+    }
+
+    /// changes and saves the multi-timbral settings, then loads and drops parts to match.
+    fn set_parts(&mut self, change: impl FnOnce(&mut MultiConfig)) {
+        save_config(|config| change(&mut config.multi));
+        let config = CONFIG.read().unwrap().clone();
+
+        if let Ok(mut synth) = self.synth.write() {
+            synth.load_parts(&config);
+        }
+    }
+
+    /// changes and saves the keyboard zones, then loads the parts they play.
+    fn set_zones(&mut self, change: impl FnOnce(&mut ZoneConfig)) {
+        save_config(|config| change(&mut config.zones));
+        let config = CONFIG.read().unwrap().clone();
+
+        if let Ok(mut synth) = self.synth.write() {
+            synth.load_parts(&config);
+        }
+    }
+
+    fn set_part(&mut self, index: usize, part: PartConfig) {
+        let old = CONFIG.read().unwrap().multi.parts.get(index).copied();
+
+        self.set_parts(|multi| {
+            if let Some(p) = multi.parts.get_mut(index) {
+                *p = part;
+            }
+        });
+
+        // a new engine starts on the part's bank and program already.
+        if old.is_some_and(|old| {
+            old.engine == part.engine && (old.bank, old.program) != (part.bank, part.program)
+        }) {
+            if let Ok(mut synth) = self.synth.write() {
+                synth.set_part(Some(index));
+                midi::recall(&mut synth, part.bank, part.program);
+                synth.set_part(None);
+            }
+
+            // the patch recalled can be on another engine.
+            save_config(|_| {});
+        }
+    }
+
+    /// stores part `index` as the patch at the bank and program it is on, keeping the name of
+    /// the patch it replaces.
+    fn store_patch(&mut self, index: usize) {
+        let Ok(mut synth) = self.synth.write() else {
+            return;
+        };
+
+        let Some(state) = synth.part_state(index).copied() else {
+            return;
+        };
+
+        let name = patches::get(state.bank, state.program)
+            .map(|patch| patch.name)
+            .unwrap_or_else(|| format!("{} {}-{}", state.engine, state.bank, state.program));
+
+        synth.set_part(Some(index));
+        let patch = synth.patch(name);
+        synth.set_part(None);
+        drop(synth);
+
+        patches::store(state.bank, state.program, patch);
+    }
+
+    fn set_mpe(&mut self, mpe: MpeConfig) {
+        let old = CONFIG.read().unwrap().mpe;
+
+        if let Ok(mut synth) = self.synth.write() {
+            // the member engines are on the first part.
+            if old.member_bend != mpe.member_bend {
+                synth.set_member_range(BendRange::symmetric(mpe.member_bend));
+            }
+
+            // notes on channels whose role changed would never get their note off.
+            if (old.enabled, old.lower, old.upper) != (mpe.enabled, mpe.lower, mpe.upper) {
+                synth.send_to(0, SynthCmd::AllNotesOff);
+            }
+        }
+
+        save_config(|config| config.mpe = mpe);
+
+        // the member engines are only built while there's a zone for them.
+        if old.has_zone() != mpe.has_zone() {
+            let config = CONFIG.read().unwrap().clone();
+
+            if let Ok(mut synth) = self.synth.write() {
+                synth.load_parts(&config);
+            }
+        }
+    }
+
+    /// restarts the output with the picked settings and saves them if the device took them.
+    fn apply_audio_settings(&mut self) {
+        let Ok(mut synth) = self.synth.write() else {
+            return;
+        };
+
+        match synth.set_audio_params(self.audio) {
+            Ok(()) => {
+                self.audio_error = None;
+
+                let audio = self.audio;
+
+                save_config(|config| {
+                    config.audio.sample_rate = audio.sample_rate;
+                    config.audio.buffer_size = audio.buffer_size;
+                });
+            }
+            Err(e) => {
+                self.audio = synth.audio_params();
+                self.audio_error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn settings(&self) -> Element<Message, Theme, Renderer> {
+        let latency = self.synth.read().unwrap().latency();
+        let ms = |d: std::time::Duration| format!("{:.1} ms", d.as_secs_f64() * 1000.0);
+        let measured = latency.measured.map(ms).unwrap_or_else(|| "-".into());
+        let period = latency
+            .callback_period
+            .map(ms)
+            .unwrap_or_else(|| "-".into());
+
+        let status = match self.synth.read().unwrap().audio_status() {
+            Some(AudioStatus::Running {
+                backend,
+                params,
+                fallback,
+            }) => format!(
+                "running on {backend} at {} Hz, {} frame buffers, {} channels{}",
+                params.sample_rate,
+                params.buffer_size,
+                params.channels,
+                if *fallback {
+                    " (fallback, the device refused the settings above)"
+                } else {
+                    ""
+                }
+            ),
+            Some(AudioStatus::Failed { error, .. }) => format!("failed: {error}"),
+            None => "no output device".into(),
+        };
+
+        let mut page = column![
+            text("Audio").size(24),
+            text(status),
+            row![
+                text("Sample rate"),
+                pick_list(
+                    &SAMPLE_RATES[..],
+                    Some(self.audio.sample_rate),
+                    Message::SetSampleRate
+                ),
+                text("Buffer size"),
+                pick_list(
+                    &BUFFER_SIZES[..],
+                    Some(self.audio.buffer_size),
+                    Message::SetBufferSize
+                ),
+                button("Apply").on_press(Message::ApplyAudioSettings),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            text(format!(
+                "latency: {} theoretical, {measured} measured (callback every {period})",
+                ms(latency.theoretical)
+            )),
+        ]
+        .spacing(10)
+        .padding(10);
+
+        if let Some(e) = self.audio_error.as_ref() {
+            page = page.push(text(format!("could not apply: {e}")));
+        }
+
+        page.width(Length::Fill).height(Length::Fill).into()
+    }
+
+    /// the synth's receive channel, and every input device with its connection state, a switch,
+    /// and a channel filter.
+    fn midi_selection(&self) -> Element<Message, Theme, Renderer> {
+        let filter = CONFIG.read().unwrap().midi.clone();
+        let deadzone = CONFIG.read().unwrap().bend.deadzone;
+        let midi_out = CONFIG.read().unwrap().midi_out;
+        let range = self.synth.read().unwrap().bend_range();
+        let registered = devices::devices();
+        // sources the registry doesn't know, like the desktop's command line inputs.
+        let mut others = KNOWN_SOURCES.read().unwrap().clone();
+        others.extend(filter.devices.keys().cloned());
+        others.extend(filter.disabled.iter().cloned());
+
+        for device in registered.iter() {
+            others.remove(&device.source_name());
+        }
+
+        let mut page = column![
+            text("MIDI").size(24),
+            row![
+                text("Synth receives on"),
+                pick_list(
+                    &ReceiveChannel::ALL[..],
+                    Some(filter.receive_channel),
+                    Message::SetReceiveChannel
+                ),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                text("Bend up"),
+                pick_list(
+                    &BEND_SEMITONES[..],
+                    Some(range.up.round() as u8),
+                    move |up| Message::SetBendRange(BendRange {
+                        up: up as f32,
+                        ..range
+                    })
+                ),
+                text("down"),
+                pick_list(
+                    &BEND_SEMITONES[..],
+                    Some(range.down.round() as u8),
+                    move |down| Message::SetBendRange(BendRange {
+                        down: down as f32,
+                        ..range
+                    })
+                ),
+                text("semitones, deadzone"),
+                slider(0.0..=0.1, deadzone, Message::SetBendDeadzone)
+                    .step(0.005)
+                    .on_release(Message::SaveConfig),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            row![
+                button("CC mappings").on_press(Message::OpenMappings),
+                button("Monitor").on_press(Message::OpenMonitor),
+            ]
+            .spacing(10),
+            row![
+                text("MIDI out on"),
+                pick_list(
+                    &CHANNELS[..],
+                    Some(midi_out.channel),
+                    Message::SetOutChannel
+                ),
+                checkbox("Thru", midi_out.thru).on_toggle(Message::SetThru),
+                checkbox("Send on-screen notes", midi_out.local_notes)
+                    .on_toggle(Message::SetSendLocalNotes),
+                checkbox("Send parameters as CCs", midi_out.params)
+                    .on_toggle(Message::SetSendParams),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+            self.mpe_row(),
+            text("Devices"),
+        ]
+        .spacing(10)
+        .padding(10);
+
+        for device in registered {
+            let status = format!(
+                "{}, {} in / {} out",
+                if device.connected {
+                    "connected"
+                } else {
+                    "disconnected"
+                },
+                device.inputs,
+                device.outputs
+            );
+
+            page = page.push(device_row(&filter, device.source_name(), Some(status)));
+        }
+
+        for device in others {
+            page = page.push(device_row(&filter, device, None));
+        }
+
+        page.width(Length::Fill).height(Length::Fill).into()
+    }
+
+    /// play/stop, where the song is, and the tempo and where it comes from.
+    fn transport_bar(&self) -> Element<Message, Theme, Renderer> {
+        let config = CONFIG.read().unwrap().transport;
+        let playing = transport::playing();
+        let tempo = match (config.source, transport::following()) {
+            (ClockSource::Midi, false) => "waiting for clock".to_string(),
+            _ => format!("{:.1} BPM", transport::bpm()),
+        };
+
+        let mut bar = row![
+            // a controller sending clock also says when to start and stop.
+            button(if playing { "Stop" } else { "Play" }).on_press_maybe(
+                (config.source == ClockSource::Internal).then_some(Message::TogglePlay)
+            ),
+            text(transport::position().to_string()).width(80),
+            text(tempo).width(150),
+        ]
+        .spacing(10)
+        .padding(5)
+        .align_y(Alignment::Center);
+
+        if config.source == ClockSource::Internal {
+            bar = bar.push(button("Tap").on_press(Message::Tap));
+            bar = bar.push(
+                slider(
+                    transport::MIN_BPM..=transport::MAX_BPM,
+                    config.bpm,
+                    move |bpm| Message::DragTransport(TransportConfig { bpm, ..config }),
+                )
+                .step(0.1)
+                .on_release(Message::SaveConfig),
+            );
+            bar = bar.push(checkbox("Send clock", config.send_clock).on_toggle(
+                move |send_clock| {
+                    Message::SetTransport(TransportConfig {
+                        send_clock,
+                        ..config
+                    })
+                },
+            ));
+        } else {
+            bar = bar.push(horizontal_space());
+        }
+
+        bar.push(pick_list(
+            &ClockSource::ALL[..],
+            Some(config.source),
+            move |source| Message::SetTransport(TransportConfig { source, ..config }),
+        ))
+        .into()
+    }
+
+    /// the multi-timbral parts, their channels, engines, banks, programs, and levels, and the
+    /// patches stored where they are.
+    fn parts(&self) -> Element<Message, Theme, Renderer> {
+        let multi = CONFIG.read().unwrap().multi.clone();
+        let selected = self.synth.read().unwrap().selected_part();
+
+        let mut page = column![
+            text("Parts").size(24),
+            checkbox("Multi-timbral", multi.enabled).on_toggle(Message::SetMulti),
+            text(if multi.enabled {
+                "each part plays the channel set on it. MPE zones play on part 1."
+            } else {
+                "part 1 plays on the receive channel, turn on multi-timbral for the rest."
+            }),
+        ]
+        .spacing(10)
+        .padding(10);
+
+        for (index, part) in multi.parts.iter().copied().enumerate().take(MAX_PARTS) {
+            let loaded = multi.part(index).is_some();
+            let name = if index == selected {
+                format!("Part {} (editing)", index + 1)
+            } else {
+                format!("Part {}", index + 1)
+            };
+
+            page = page.push(
+                row![
+                    button(text(name)).width(150).on_press_maybe(
+                        (loaded && index != selected).then_some(Message::SelectPart(index))
+                    ),
+                    text("channel"),
+                    pick_list(&CHANNELS[..], Some(part.channel), move |channel| {
+                        Message::SetPart(index, PartConfig { channel, ..part })
+                    }),
+                    pick_list(&PartEngine::ALL[..], Some(part.engine), move |engine| {
+                        Message::SetPart(index, PartConfig { engine, ..part })
+                    }),
+                    text("bank"),
+                    pick_list(&BANKS[..], Some(part.bank), move |bank| {
+                        Message::SetPart(index, PartConfig { bank, ..part })
+                    }),
+                    text("program"),
+                    pick_list(&PROGRAMS[..], Some(part.program), move |program| {
+                        Message::SetPart(index, PartConfig { program, ..part })
+                    }),
+                    text(patch_name(patches::get(part.bank, part.program))).width(150),
+                    button("Store").on_press_maybe(loaded.then_some(Message::StorePatch(index))),
+                    text("level"),
+                    slider(0.0..=1.0, part.level, move |level| {
+                        Message::DragPart(index, PartConfig { level, ..part })
+                    })
+                    .step(0.01)
+                    .on_release(Message::SaveConfig),
+                    button("Remove")
+                        .on_press_maybe((index > 0).then_some(Message::RemovePart(index))),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        page.push(
+            button("Add part")
+                .on_press_maybe((multi.parts.len() < MAX_PARTS).then_some(Message::AddPart)),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    /// the keyboard zones: which keys and velocities on which channel play which part, and how
+    /// far they are transposed.
+    fn zones(&self) -> Element<Message, Theme, Renderer> {
+        let (zones, parts) = {
+            let config = CONFIG.read().unwrap();
+            (config.zones.clone(), config.multi.parts.len())
+        };
+        let parts: Vec<PartNumber> = (0..parts.clamp(1, MAX_PARTS)).map(PartNumber).collect();
+
+        let mut page = column![
+            text("Zones").size(24),
+            checkbox("Zones", zones.enabled).on_toggle(Message::SetZonesEnabled),
+            text("notes in a zone play its part. add parts on the Parts screen."),
+        ]
+        .spacing(10)
+        .padding(10);
+
+        for (index, zone) in zones.list.iter().copied().enumerate() {
+            let set = move |zone: Zone| Message::SetZone(index, zone);
+
+            page = page.push(
+                row![
+                    pick_list(
+                        &ReceiveChannel::ALL[..],
+                        Some(zone.channel),
+                        move |channel| { set(Zone { channel, ..zone }) }
+                    ),
+                    text("keys"),
+                    pick_list(&KEYS[..], Some(Key(zone.low_key)), move |Key(low_key)| {
+                        set(Zone {
+                            low_key,
+                            high_key: zone.high_key.max(low_key),
+                            ..zone
+                        })
+                    }),
+                    text("to"),
+                    pick_list(&KEYS[..], Some(Key(zone.high_key)), move |Key(high_key)| {
+                        set(Zone {
+                            high_key,
+                            low_key: zone.low_key.min(high_key),
+                            ..zone
+                        })
+                    }),
+                    text("velocity"),
+                    pick_list(
+                        &VELOCITIES[..],
+                        Some(zone.low_velocity),
+                        move |low_velocity| {
+                            set(Zone {
+                                low_velocity,
+                                high_velocity: zone.high_velocity.max(low_velocity),
+                                ..zone
+                            })
+                        }
+                    ),
+                    text("to"),
+                    pick_list(
+                        &VELOCITIES[..],
+                        Some(zone.high_velocity),
+                        move |high_velocity| {
+                            set(Zone {
+                                high_velocity,
+                                low_velocity: zone.low_velocity.min(high_velocity),
+                                ..zone
+                            })
+                        }
+                    ),
+                    pick_list(parts.clone(), Some(PartNumber(zone.part)), move |part| {
+                        set(Zone {
+                            part: part.0,
+                            ..zone
+                        })
+                    }),
+                    text("transpose"),
+                    pick_list(&TRANSPOSES[..], Some(zone.transpose), move |transpose| {
+                        set(Zone { transpose, ..zone })
+                    }),
+                    button("Remove").on_press(Message::RemoveZone(index)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        page.push(button("Add zone").on_press(Message::AddZone))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// MPE on or off, the zones, and where per note expression goes.
+    fn mpe_row(&self) -> Element<Message, Theme, Renderer> {
+        let mpe = CONFIG.read().unwrap().mpe;
+
+        row![
+            checkbox("MPE", mpe.enabled)
+                .on_toggle(move |enabled| Message::SetMpe(MpeConfig { enabled, ..mpe })),
+            text("lower zone"),
+            pick_list(&MPE_MEMBERS[..], Some(mpe.lower), move |members| {
+                let mut mpe = mpe;
+                mpe.set_zone(mpe::LOWER_MASTER, members);
+                Message::SetMpe(mpe)
+            }),
+            text("upper zone"),
+            pick_list(&MPE_MEMBERS[..], Some(mpe.upper), move |members| {
+                let mut mpe = mpe;
+                mpe.set_zone(mpe::UPPER_MASTER, members);
+                Message::SetMpe(mpe)
+            }),
+            text("note bend"),
+            pick_list(
+                &MEMBER_BEND_SEMITONES[..],
+                Some(mpe.member_bend.round() as u8),
+                move |semitones| Message::SetMpe(MpeConfig {
+                    member_bend: semitones as f32,
+                    ..mpe
+                })
+            ),
+            text("timbre to knob"),
+            pick_list(&TIMBRE_KNOBS[..], Some(mpe.timbre_knob), move |knob| {
+                Message::SetMpe(MpeConfig {
+                    timbre_knob: knob,
+                    ..mpe
+                })
+            }),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .into()
+    }
+
+    /// the CC mapping table. every binding can be edited in place or relearned, and any target
+    /// can be learned from the bottom row.
+    fn mappings(&self) -> Element<Message, Theme, Renderer> {
+        let bindings = CONFIG.read().unwrap().mappings.bindings.clone();
+
+        let mut page = column![text("CC mappings").size(24)]
+            .spacing(10)
+            .padding(10);
+
+        if let Some(target) = learn::learning() {
+            page = page.push(
+                row![
+                    text(format!("move a controller to bind it to {target}")).width(Length::Fill),
+                    button("Cancel").on_press(Message::CancelLearn),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        for (i, binding) in bindings.into_iter().enumerate() {
+            page = page.push(
+                row![
+                    text("CC"),
+                    pick_list(&BINDABLE_CCS[..], Some(binding.control), move |control| {
+                        Message::SetBinding(i, Binding { control, ..binding })
+                    }),
+                    pick_list(&Target::ALL[..], Some(binding.target), move |target| {
+                        Message::SetBinding(i, Binding { target, ..binding })
+                    }),
+                    text("Min"),
+                    slider(0.0..=1.0, binding.min, move |min| {
+                        Message::DragBinding(i, Binding { min, ..binding })
+                    })
+                    .step(0.01)
+                    .on_release(Message::SaveConfig),
+                    text("Max"),
+                    slider(0.0..=1.0, binding.max, move |max| {
+                        Message::DragBinding(i, Binding { max, ..binding })
+                    })
+                    .step(0.01)
+                    .on_release(Message::SaveConfig),
+                    pick_list(&Curve::ALL[..], Some(binding.curve), move |curve| {
+                        Message::SetBinding(i, Binding { curve, ..binding })
+                    }),
+                    checkbox("Invert", binding.invert).on_toggle(move |invert| {
+                        Message::SetBinding(i, Binding { invert, ..binding })
+                    }),
+                    button("Learn").on_press(Message::Learn(binding.target)),
+                    button("Remove").on_press(Message::RemoveBinding(i)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        page = page.push(
+            row![
+                text("Learn a new binding for"),
+                pick_list(&Target::ALL[..], learn::learning(), Message::Learn),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+
+        page.width(Length::Fill).height(Length::Fill).into()
+    }
+
+    /// the MIDI monitor: the messages coming in, newest first, with filters. the ones shown
+    /// greyed out didn't reach the synth.
+    fn monitor(&self) -> Element<Message, Theme, Renderer> {
+        let filter = self.monitor_filter;
+        let paused = monitor::is_paused();
+        let entries = monitor::entries(&filter, MONITOR_ROWS);
+
+        let controls = row![
+            text("MIDI monitor").size(24).width(Length::Fill),
+            pick_list(
+                &KIND_FILTERS[..],
+                Some(KindFilter(filter.kind)),
+                move |kind| {
+                    Message::SetMonitorFilter(monitor::Filter {
+                        kind: kind.0,
+                        ..filter
+                    })
+                }
+            ),
+            pick_list(
+                &ReceiveChannel::ALL[..],
+                Some(filter.channel),
+                move |channel| { Message::SetMonitorFilter(monitor::Filter { channel, ..filter }) }
+            ),
+            checkbox("Clock", filter.clock).on_toggle(move |clock| {
+                Message::SetMonitorFilter(monitor::Filter { clock, ..filter })
+            }),
+            button(if paused { "Resume" } else { "Pause" }).on_press(Message::ToggleMonitorPause),
+            button("Clear").on_press(Message::ClearMonitor),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let mut list = column![].spacing(2);
+
+        if entries.is_empty() {
+            list = list.push(text(if paused {
+                "paused."
+            } else {
+                "nothing yet. play something on a controller."
+            }));
+        }
+
+        for entry in &entries {
+            list = list.push(monitor_row(entry));
+        }
+
+        column![controls, scrollable(list).height(Length::Fill)]
+            .spacing(10)
+            .padding(10)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// a warning with a retry button while the audio output is down.
+    fn audio_banner(&self) -> Option<Element<Message, Theme, Renderer>> {
+        let synth = self.synth.read().unwrap();
+        let Some(AudioStatus::Failed { error, retry_at }) = synth.audio_status() else {
+            return None;
+        };
+        let wait = retry_at.saturating_duration_since(std::time::Instant::now());
+
+        Some(
+            row![
+                text(format!(
+                    "no audio: {error}. retrying in {}s",
+                    wait.as_secs_f32().ceil()
+                ))
+                .color(Color::from_rgb(1.0, 0.4, 0.4))
+                .width(Length::Fill),
+                button("Retry now").on_press(Message::RetryAudio),
+            ]
+            .spacing(10)
+            .padding(10)
+            .align_y(Alignment::Center)
+            .into(),
+        )
+    }
+
+    /// a one octave keyboard for playing without a controller. shown under every synth screen.
+    /// sliding off a key lets go of it, so notes can't get stuck.
+    fn keyboard(&self) -> Element<Message, Theme, Renderer> {
+        let mut keys = row![button("<").on_press(Message::ShiftOctave(-1))]
+            .spacing(2)
+            .align_y(Alignment::Center);
+
+        for key in self.keyboard_base..(self.keyboard_base + KEYBOARD_KEYS).min(128) {
+            let name = NOTE_NAMES[key as usize % 12];
+            let black = name.ends_with('#');
+            let label = if key % 12 == 0 {
+                format!("{name}{}", key as i16 / 12 - 1)
+            } else {
+                name.to_string()
+            };
+
+            keys = keys.push(
+                mouse_area(
+                    container(text(label).center())
+                        .width(Length::Fill)
+                        .height(80)
+                        .align_y(if black {
+                            Alignment::Start
+                        } else {
+                            Alignment::End
+                        })
+                        .style(move |_| key_style(black)),
+                )
+                .on_press(Message::KeyDown(key))
+                .on_release(Message::KeyUp(key))
+                .on_exit(Message::KeyUp(key)),
+            );
+        }
+
+        keys.push(button(">").on_press(Message::ShiftOctave(1)))
+            .padding(10)
+            .into()
+    }
+
+    /// master pan, width, and voice spread. shown under every synth screen.
+    fn stereo(&self) -> Element<Message, Theme, Renderer> {
+        let stereo = *self.synth.read().unwrap().stereo();
+
+        row![
+            text("Pan"),
+            slider(-1.0..=1.0, stereo.pan, |pan| Message::SetStereo(
+                StereoParam::Pan(pan)
+            ))
+            .step(0.01),
+            text("Width"),
+            slider(0.0..=2.0, stereo.width, |width| Message::SetStereo(
+                StereoParam::Width(width)
+            ))
+            .step(0.01),
+            text("Spread"),
+            color_slider(stereo.spread, |spread| Message::SetStereo(
+                StereoParam::Spread(spread)
+            )),
+            checkbox("Unison", stereo.unison)
+                .on_toggle(|unison| Message::SetStereo(StereoParam::Unison(unison))),
+            // in learn mode moving a slider waits for a controller to bind to it.
+            checkbox("Learn", self.learn_mode).on_toggle(|_| Message::ToggleLearn),
+        ]
+        .spacing(10)
+        .padding(10)
+        .align_y(Alignment::Center)
+        .into()
+    }
+
+    fn osc(&self) -> Element<Message, Theme, Renderer> {
+        // fn osc(&self, engine: &WaveTableEngine) -> Element<Message> {
+        let osc_display = move |i: usize| -> Row<'_, Message> {
+            // text(format!("Osc {i}"))
+            //     .width(Length::Fill)
+            //     .height(Length::Fill)
+            //     .center()
+            row![
+                // text(format!("{}", i + 1))
+                // .width(Length::Fill)
+                // .height(Length::Fill)
+                // .center(),
+
+            ]
+        };
+
+        column![
+            // text("Osc")
+            // .width(Length::Fill)
+            // .height(Length::Fill)
+            // .center()
+            osc_display(0),
+            osc_display(1),
+            osc_display(2),
+        ]
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+}
+
+impl Controls {
+    // fn examples(&self) -> PickList<Example, &[Example], Example, Message> {
+    //     pick_list(
+    //         &EXAMPLES[..],
+    //         Some(self.selected_example),
+    //         Message::ExampleSelected,
+    //     )
+    // }
+
+    // fn integration(&self) -> Element<Message, Theme, Renderer> {
+    //     // let sliders = row![
+    //     //     color_slider(self.background_color.r, Message::RedChanged),
+    //     //     color_slider(self.background_color.g, Message::GreenChanged),
+    //     //     color_slider(self.background_color.b, Message::BlueChanged),
+    //     // ]
+    //     // .width(Length::Fill)
+    //     // .spacing(20);
+    //
+    //     container(
+    //         column![
+    //             Space::with_height(20),
+    //             // self.examples(),
+    //             vertical_space(),
+    //             row![
+    //                 text!("{:?}", self.backgrouiced_gitnd_color).size(14),
+    //                 horizontal_space(),
+    //             ],
+    //             // text_input("Placeholder", &self.input).on_input(Message::InputChanged),
+    //             // sliders,
+    //             Space::with_height(20),
+    //         ]
+    //         .align_x(Alignment::Center)
+    //         .spacing(10),
+    //     )
+    //     .padding(10)
+    //     .into()
+    // }
+
+    // fn counter(&self) -> Element<Message, Theme, Renderer> {
+    //     container(
+    //         column![
+    //             Space::with_height(30),
+    //             self.examples(),
+    //             vertical_space(),
+    //             button("Increment").on_press(Message::Inc),
+    //             text!("{}", self.value).size(50),
+    //             button("Decrement").on_press(Message::Dec),
+    //             vertical_space(),
+    //             Space::with_height(100),
+    //         ]
+    //         .align_x(Alignment::Center)
+    //         .spacing(10),
+    //     )
+    //     .center(Length::Fill)
+    //     .style(add_background)
+    //     .into()
+    // }
+    //
+    // fn text_editor(&self) -> Element<Message, Theme, Renderer> {
+    //     container(
+    //         column![
+    //             Space::with_height(30),
+    //             self.examples(),
+    //             vertical_space(),
+    //             text_editor::<Message, Theme, Renderer>(&self.editor)
+    //                 .height(400)
+    //                 .on_action(Message::EditorAction),
+    //             vertical_space(),
+    //         ]
+    //         .align_x(Alignment::Center),
+    //     )
+    //     .padding(10)
+    //     .center(Length::Fill)
+    //     .style(add_background)
+    //     .into()
+    // }
+}
+
+/// a device's switch and channel filter, `status` is shown under its name.
+fn device_row(
+    filter: &ChannelFilter,
+    device: String,
+    status: Option<String>,
+) -> Element<'static, Message, Theme, Renderer> {
+    let channel = filter.device(&device);
+    let enabled = filter.enabled(&device);
+    let mut label = column![text(device.clone())];
+
+    if let Some(status) = status {
+        label = label.push(text(status).size(12));
+    }
+
+    let name = device.clone();
+
+    row![
+        label.width(Length::Fill),
+        checkbox("Enabled", enabled)
+            .on_toggle(move |enabled| Message::SetDeviceEnabled(name.clone(), enabled)),
+        pick_list(&ReceiveChannel::ALL[..], Some(channel), move |channel| {
+            Message::SetDeviceChannel(device.clone(), channel)
+        }),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+fn key_style(black: bool) -> container::Style {
+    let (key, label) = if black {
+        (Color::BLACK, Color::WHITE)
+    } else {
+        (Color::WHITE, Color::BLACK)
+    };
+
+    container::Style {
+        text_color: Some(label),
+        background: Some(key.into()),
+        border: Border {
+            color: Color::from_rgb(0.5, 0.5, 0.5),
+            width: 1.0,
+            radius: 2.0.into(),
+        },
+        ..container::Style::default()
+    }
+}
+
+fn add_background(theme: &Theme) -> container::Style {
+    theme.palette().background.into()
+}
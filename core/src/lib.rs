@@ -0,0 +1,566 @@
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use iced_wgpu::graphics::Viewport;
+use iced_wgpu::{wgpu, Engine, Renderer};
+use iced_winit::conversion;
+use iced_winit::core::{mouse, renderer, Font, Pixels, Size, Theme};
+use iced_winit::runtime::{program, Debug};
+pub use iced_winit::winit;
+use lazy_static::lazy_static;
+use log::{debug, error};
+use log::{info, warn};
+use midi_control::MidiMessage;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{spawn, JoinHandle};
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use stepper_synth_backend::synth_engines::SynthModule;
+use audio::BackendConfig;
+use config::{Config, CONFIG};
+use midi::output::{spawn_outputs, MidiOutput};
+use midi::devices::{self, MidiDevice};
+use midi::source::{spawn_source, MidiSource};
+use midi::MidiEvent;
+use patches::{PatchStore, PATCHES};
+use transport::TransportEvent;
+use synth::{make_synth, spawn_watchdog, BendRange, TabSynth};
+// use stepper_synth_backend::synth_engines::organ::organ::Organ;
+use stepper_synth_backend::{
+    synth_engines::{Synth, SynthEngine},
+    SampleGen, CHANNEL_SIZE, SAMPLE_RATE,
+};
+use stepper_synth_backend::{KnobCtrl, MidiControlled};
+// use synth::make_synth;
+use wgpu::{Device, Instance, Queue, TextureFormat};
+use winit::application::ApplicationHandler;
+use winit::event::{DeviceEvent, DeviceId, ElementState, StartCause, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::window::{Window, WindowId};
+
+pub mod audio;
+pub mod config;
+mod controls;
+pub mod midi;
+pub mod patches;
+pub mod render;
+mod scene;
+pub mod transport;
+
+lazy_static! {
+    // pub static ref TAB_SYNTH: Arc<Mutex<Option<synth::TabSynth>>> = Arc::new(Mutex::new(None));
+    pub static ref CBEAM_CHANNELS: (Sender<MidiEvent>, Receiver<MidiEvent>) = unbounded();
+    pub static ref MIDI_SEND: Sender<MidiEvent> = CBEAM_CHANNELS.0.clone();
+    pub static ref MIDI_RECV: Receiver<MidiEvent> = CBEAM_CHANNELS.1.clone();
+}
+// pub static TAB_SYNTH: Arc<Mutex<Option<synth::TabSynth>>> = Arc::new(Mutex::new(None));
+
+use controls::Controls;
+use scene::Scene;
+pub mod synth;
+
+/// the parts of the app that differ between the android shell and the desktop build.
+pub trait Platform: 'static {
+    type Clipboard: iced_core::Clipboard;
+
+    fn clipboard(&self) -> Self::Clipboard;
+    fn show_keyboard(&self);
+    fn hide_keyboard(&self);
+
+    /// the audio backend to start the synth on.
+    fn audio_backend(&self) -> BackendConfig {
+        BackendConfig::default()
+    }
+
+    /// a private, writable directory for the config file. `None` keeps every setting in memory.
+    fn data_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// the MIDI inputs to start with, they all feed the same router.
+    fn midi_sources(&self) -> Vec<Box<dyn MidiSource>> {
+        Vec::new()
+    }
+
+    /// where MIDI thru, on-screen notes, and parameter CCs are sent.
+    fn midi_outputs(&self) -> Vec<Box<dyn MidiOutput>> {
+        Vec::new()
+    }
+}
+
+/// makes the synth, starts the MIDI router, and runs the UI. called by the platform entry points
+/// once they have built their event loop.
+pub fn run<P: Platform>(event_loop: EventLoop<UserEvent>, platform: P) {
+    let proxy = event_loop.create_proxy();
+    midi::devices::listen(proxy.clone());
+    midi::monitor::listen(proxy.clone());
+
+    log::info!("proxy event loop made");
+
+    let config = Config::load(platform.data_dir().as_deref());
+    log::info!("config loaded from {:?}", config.path);
+    let params = config.audio.params();
+    let bend_range = config.bend.range;
+    let member_range = BendRange::symmetric(config.mpe.member_bend);
+    transport::configure(config.transport);
+    *CONFIG.write().unwrap() = config;
+    *PATCHES.write().unwrap() = PatchStore::load(platform.data_dir().as_deref());
+
+    // needed bc audio output will fail if its started too soon.
+    // TAB_SYNTH.lock().unwrap().replace(make_synth());
+    let mut synth = make_synth(&platform.audio_backend(), params);
+    synth.set_bend_range(bend_range);
+    synth.set_member_range(member_range);
+    synth.load_parts(&CONFIG.read().unwrap());
+    // let synth = Organ::new();
+    log::info!("synth made");
+
+    let synth = Arc::new(RwLock::new(synth));
+    log::info!("synth stored in a mutex/rw_lock");
+
+    let midi_sources = platform.midi_sources();
+    let _midi_out = spawn_outputs(platform.midi_outputs());
+    let proxy_for_transport = proxy.clone();
+    let mut app = App::new(proxy, synth.clone(), platform);
+
+    log::info!("app made");
+
+    let _jh = midi::spawn_router(synth.clone());
+    let _watchdog = spawn_watchdog(synth.clone());
+    let _clock = transport::spawn_clock();
+    let _beats = spawn_transport_events(proxy_for_transport);
+    let _sources: Vec<_> = midi_sources
+        .into_iter()
+        .map(|source| spawn_source(source, MIDI_SEND.clone()))
+        .collect();
+
+    log::info!("starting main event loop...");
+
+    event_loop.run_app(&mut app).expect("Should run event loop");
+}
+
+#[derive(Debug)]
+pub enum UserEvent {
+    ShowKeyboard,
+    HideKeyboard,
+    Tick,
+    /// a MIDI device was plugged in.
+    MidiDeviceAdded(MidiDevice),
+    /// a MIDI device was unplugged.
+    MidiDeviceRemoved(devices::DeviceId),
+    /// the tempo changed, or the transport started, stopped, or moved on a beat.
+    Transport(TransportEvent),
+    /// messages came in while the MIDI monitor is open.
+    MidiMonitor,
+}
+
+/// forwards transport events to the event loop so the transport readout moves with the music
+/// rather than once a tick.
+fn spawn_transport_events(proxy: EventLoopProxy<UserEvent>) -> JoinHandle<()> {
+    let events = transport::subscribe();
+
+    spawn(move || {
+        for event in events {
+            if proxy.send_event(UserEvent::Transport(event)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+struct App<P: Platform> {
+    proxy: EventLoopProxy<UserEvent>,
+    app_data: Option<AppData<P::Clipboard>>,
+    resized: bool,
+    cursor_position: Option<winit::dpi::PhysicalPosition<f64>>,
+    modifiers: ModifiersState,
+    value: AtomicU32,
+    running: Arc<AtomicBool>,
+    synth: Arc<RwLock<TabSynth>>,
+    platform: P,
+}
+
+struct AppData<C> {
+    state: program::State<Controls>,
+    scene: Scene,
+    window: Arc<Window>,
+    device: Device,
+    queue: Queue,
+    surface: wgpu::Surface<'static>,
+    format: TextureFormat,
+    engine: Engine,
+    renderer: Renderer,
+    clipboard: C,
+    viewport: Viewport,
+    debug: Debug,
+}
+
+impl<P: Platform> App<P> {
+    fn new(proxy: EventLoopProxy<UserEvent>, synth: Arc<RwLock<TabSynth>>, platform: P) -> Self {
+        Self {
+            proxy,
+            app_data: None,
+            resized: false,
+            cursor_position: None,
+            modifiers: ModifiersState::default(),
+            value: AtomicU32::new(0),
+            running: Arc::new(AtomicBool::new(false)),
+            synth,
+            platform,
+        }
+    }
+}
+
+impl<P: Platform> ApplicationHandler<UserEvent> for App<P> {
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, _cause: StartCause) {
+        // log::info!("New events cause {:?}", cause);
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        log::info!("Resumed");
+        if self.app_data.is_some() {
+            log::info!("Already initialized, skipping");
+            return;
+        }
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let attrs = Window::default_attributes();
+        let window = Arc::new(event_loop.create_window(attrs).unwrap());
+
+        let physical_size = window.inner_size();
+        let viewport = Viewport::with_physical_size(
+            Size::new(physical_size.width, physical_size.height),
+            window.scale_factor(),
+        );
+        let clipboard = self.platform.clipboard();
+
+        let surface = instance
+            .create_surface(window.clone())
+            .expect("Create window surface");
+
+        let (format, adapter, device, queue) = futures::executor::block_on(async {
+            let adapter =
+                wgpu::util::initialize_adapter_from_env_or_default(&instance, Some(&surface))
+                    .await
+                    .expect("Create adapter");
+
+            let adapter_features = adapter.features();
+
+            let capabilities = surface.get_capabilities(&adapter);
+
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        label: None,
+                        required_features: adapter_features & wgpu::Features::default(),
+                        required_limits: wgpu::Limits::default(),
+                        memory_hints: wgpu::MemoryHints::MemoryUsage,
+                    },
+                    None,
+                )
+                .await
+                .expect("Request device");
+
+            (
+                capabilities
+                    .formats
+                    .iter()
+                    .copied()
+                    .find(wgpu::TextureFormat::is_srgb)
+                    .or_else(|| capabilities.formats.first().copied())
+                    .expect("Get preferred format"),
+                adapter,
+                device,
+                queue,
+            )
+        });
+
+        surface.configure(
+            &device,
+            &wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format,
+                width: physical_size.width,
+                height: physical_size.height,
+                present_mode: wgpu::PresentMode::AutoVsync,
+                alpha_mode: wgpu::CompositeAlphaMode::Auto,
+                view_formats: vec![],
+                desired_maximum_frame_latency: 2,
+            },
+        );
+
+        let scene = Scene::new(&device, format);
+        let controls = Controls::new(self.proxy.clone(), self.synth.clone());
+
+        let mut debug = Debug::new();
+        let engine = Engine::new(&adapter, &device, &queue, format, None);
+        let mut renderer = Renderer::new(&device, &engine, Font::default(), Pixels::from(16));
+
+        let state =
+            program::State::new(controls, viewport.logical_size(), &mut renderer, &mut debug);
+
+        event_loop.set_control_flow(ControlFlow::Wait);
+
+        self.cursor_position = None;
+        self.modifiers = ModifiersState::default();
+
+        let app_data = AppData {
+            state,
+            scene,
+            window,
+            device,
+            queue,
+            surface,
+            format,
+            engine,
+            renderer,
+            clipboard,
+            viewport,
+            debug,
+        };
+        self.app_data = Some(app_data);
+
+        let event_loop_running = self.running.load(Ordering::SeqCst);
+        if event_loop_running {
+            return;
+        }
+        self.running.store(true, Ordering::SeqCst);
+
+        let event_proxy = self.proxy.clone();
+        let is_running = self.running.clone();
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            if let Err(_e) = event_proxy.send_event(UserEvent::Tick) {
+                is_running.store(false, Ordering::SeqCst);
+                break;
+            }
+        });
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: UserEvent) {
+        match event {
+            UserEvent::ShowKeyboard => {
+                self.platform.show_keyboard();
+            }
+            UserEvent::HideKeyboard => {
+                self.platform.hide_keyboard();
+            }
+            UserEvent::Tick => {
+                let value = self.value.fetch_add(1, Ordering::SeqCst);
+                // log::info!("Tick event, counter value: {}", value);
+
+                // rebuilds the view so live readouts (like latency) stay current.
+                if let Some(app_data) = self.app_data.as_mut() {
+                    app_data.state.queue_message(controls::Message::Tick);
+                    app_data.window.request_redraw();
+                }
+            }
+            UserEvent::MidiDeviceAdded(_) | UserEvent::MidiDeviceRemoved(_) => {
+                // the MIDI screen reads the registry, it only needs redrawing.
+                if let Some(app_data) = self.app_data.as_mut() {
+                    app_data.state.queue_message(controls::Message::DevicesChanged);
+                    app_data.window.request_redraw();
+                }
+            }
+            UserEvent::Transport(_) | UserEvent::MidiMonitor => {
+                if let Some(app_data) = self.app_data.as_mut() {
+                    app_data.state.queue_message(controls::Message::Tick);
+                    app_data.window.request_redraw();
+                }
+            }
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        log::info!("DeviceEvent {:?}", event);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        log::info!("Window event: {:?}", event);
+
+        let Some(app_data) = self.app_data.as_mut() else {
+            return;
+        };
+
+        let AppData {
+            state,
+            scene,
+            window,
+            device,
+            queue,
+            surface,
+            format,
+            engine,
+            renderer,
+            clipboard,
+            debug,
+            ..
+        } = app_data;
+
+        match event {
+            WindowEvent::CloseRequested => {
+                event_loop.exit();
+            }
+            WindowEvent::RedrawRequested => {
+                if self.resized {
+                    let size = window.inner_size();
+
+                    app_data.viewport = Viewport::with_physical_size(
+                        Size::new(size.width, size.height),
+                        window.scale_factor(),
+                    );
+
+                    surface.configure(
+                        device,
+                        &wgpu::SurfaceConfiguration {
+                            format: *format,
+                            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                            width: size.width,
+                            height: size.height,
+                            present_mode: wgpu::PresentMode::AutoVsync,
+                            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+                            view_formats: vec![],
+                            desired_maximum_frame_latency: 2,
+                        },
+                    );
+
+                    self.resized = false;
+                }
+
+                match surface.get_current_texture() {
+                    Ok(frame) => {
+                        let mut encoder =
+                            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                label: None,
+                            });
+
+                        let program = state.program();
+
+                        let view = frame
+                            .texture
+                            .create_view(&wgpu::TextureViewDescriptor::default());
+
+                        {
+                            let mut render_pass =
+                                Scene::clear(&view, &mut encoder, program.background_color());
+                            scene.draw(&mut render_pass);
+                        }
+
+                        renderer.present::<String>(
+                            engine,
+                            device,
+                            queue,
+                            &mut encoder,
+                            None,
+                            frame.texture.format(),
+                            &view,
+                            &app_data.viewport,
+                            &[],
+                        );
+
+                        engine.submit(queue, encoder);
+                        frame.present();
+
+                        window.set_cursor(iced_winit::conversion::mouse_interaction(
+                            state.mouse_interaction(),
+                        ));
+                    }
+                    Err(error) => match error {
+                        wgpu::SurfaceError::OutOfMemory => {
+                            panic!(
+                                "Swapchain error: {error}. \
+                            Rendering cannot continue."
+                            )
+                        }
+                        _ => {
+                            window.request_redraw();
+                        }
+                    },
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(position);
+            }
+            WindowEvent::Touch(touch) => {
+                self.cursor_position = Some(touch.location);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput {
+                device_id: _,
+                ref event,
+                is_synthetic: _,
+            } => {
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    match code {
+                        KeyCode::ShiftLeft | KeyCode::ShiftRight => match event.state {
+                            ElementState::Pressed => self.modifiers |= ModifiersState::SHIFT,
+                            ElementState::Released => self.modifiers &= !ModifiersState::SHIFT,
+                        },
+                        KeyCode::ControlLeft | KeyCode::ControlRight => match event.state {
+                            ElementState::Pressed => self.modifiers |= ModifiersState::CONTROL,
+                            ElementState::Released => self.modifiers &= !ModifiersState::CONTROL,
+                        },
+                        _ => (),
+                    }
+                }
+            }
+            WindowEvent::Resized(_) => {
+                self.resized = true;
+            }
+            _ => (),
+        }
+
+        if let Some(event) =
+            iced_winit::conversion::window_event(event, window.scale_factor(), self.modifiers)
+        {
+            state.queue_event(event);
+        }
+
+        if !state.is_queue_empty() {
+            let _ = state.update(
+                app_data.viewport.logical_size(),
+                self.cursor_position
+                    .map(|p| conversion::cursor_position(p, app_data.viewport.scale_factor()))
+                    .map(mouse::Cursor::Available)
+                    .unwrap_or(mouse::Cursor::Unavailable),
+                renderer,
+                &Theme::Ferra,
+                &renderer::Style {
+                    text_color: Theme::Ferra.palette().text,
+                },
+                clipboard,
+                debug,
+            );
+
+            window.request_redraw();
+        }
+    }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {}
+}
+
+// static SERIAL_THREAD: Mutex<Option<std::thread::JoinHandle<()>>> = Mutex::new(None);
+// static FLAG_EXIT: Mutex<bool> = Mutex::new(false);
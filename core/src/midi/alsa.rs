@@ -0,0 +1,194 @@
+//! linux MIDI input through ALSA, either as a sequencer client other programs and devices can be
+//! connected to (`aconnect`, qjackctl, ...) or by reading a raw MIDI device directly.
+
+use super::output::MidiOutput;
+use super::parser::MidiParser;
+use super::source::{MidiSink, MidiSource, STOP_POLL};
+use alsa::poll::Descriptors;
+use alsa::seq::{MidiEvent, PortCap, PortType, Seq};
+use alsa::{rawmidi::Rawmidi, Direction};
+use std::ffi::CString;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// an ALSA sequencer client with one writable port named `port`.
+pub struct AlsaSeqSource {
+    pub port: String,
+}
+
+impl Default for AlsaSeqSource {
+    fn default() -> Self {
+        Self {
+            port: "synth-tab in".into(),
+        }
+    }
+}
+
+impl AlsaSeqSource {
+    fn read(&self, sink: &MidiSink, stop: &AtomicBool) -> alsa::Result<()> {
+        let seq = Seq::open(None, Some(Direction::Capture), true)?;
+        let client_name = CString::new("synth-tab").unwrap();
+        let port_name = CString::new(self.port.as_str()).unwrap_or_default();
+
+        seq.set_client_name(&client_name)?;
+        let port = seq.create_simple_port(
+            &port_name,
+            PortCap::WRITE | PortCap::SUBS_WRITE,
+            PortType::MIDI_GENERIC | PortType::APPLICATION,
+        )?;
+
+        log::info!(
+            "ALSA sequencer port {}:{port} is ready, connect inputs to it with aconnect",
+            seq.client_id()?
+        );
+
+        let decoder = MidiEvent::new(256)?;
+        decoder.enable_running_status(false);
+        // the decoder hands back bytes, the parser turns clock bytes into transport messages.
+        let mut parser = MidiParser::new();
+        let mut disconnected = false;
+        let mut input = seq.input();
+        let mut fds = (&seq, Some(Direction::Capture)).get()?;
+        let mut buf = [0u8; 256];
+
+        while !stop.load(Ordering::Relaxed) {
+            if alsa::poll::poll(&mut fds, STOP_POLL.as_millis() as i32)? == 0 {
+                continue;
+            }
+
+            while input.event_input_pending(true)? > 0 {
+                let mut event = input.event_input()?;
+
+                match decoder.decode(&mut buf, &mut event) {
+                    Ok(0) | Err(_) => {}
+                    Ok(n) => {
+                        let now = Instant::now();
+
+                        parser.push_raw(
+                            &buf[..n],
+                            |msg, raw| {
+                                disconnected |= sink.send_raw(msg, raw.to_vec(), now).is_err()
+                            },
+                            |msg| sink.system(msg, now),
+                        );
+
+                        if disconnected {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MidiSource for AlsaSeqSource {
+    fn name(&self) -> String {
+        format!("alsa-seq {}", self.port)
+    }
+
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>) {
+        if let Err(e) = self.read(&sink, &stop) {
+            log::error!("ALSA sequencer input failed: {e}");
+        }
+    }
+}
+
+/// reads a raw MIDI device, like `hw:1,0,0`.
+pub struct AlsaRawSource {
+    pub device: String,
+}
+
+impl AlsaRawSource {
+    fn read(&self, sink: &MidiSink, stop: &AtomicBool) -> alsa::Result<()> {
+        let midi = Rawmidi::new(&self.device, Direction::Capture, true)?;
+        let mut fds = midi.get()?;
+        let mut buf = [0u8; 256];
+        let mut parser = MidiParser::new();
+        let mut disconnected = false;
+
+        while !stop.load(Ordering::Relaxed) {
+            if alsa::poll::poll(&mut fds, STOP_POLL.as_millis() as i32)? == 0 {
+                continue;
+            }
+
+            match midi.io().read(&mut buf) {
+                Ok(0) => {}
+                Ok(n) => {
+                    let now = Instant::now();
+
+                    parser.push_raw(
+                        &buf[..n],
+                        |msg, raw| disconnected |= sink.send_raw(msg, raw.to_vec(), now).is_err(),
+                        |msg| sink.system(msg, now),
+                    );
+
+                    if disconnected {
+                        return Ok(());
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    log::error!("reading {} failed: {e}", self.device);
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MidiSource for AlsaRawSource {
+    fn name(&self) -> String {
+        format!("alsa-raw {}", self.device)
+    }
+
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>) {
+        if let Err(e) = self.read(&sink, &stop) {
+            log::error!("ALSA raw MIDI input {} failed: {e}", self.device);
+        }
+    }
+}
+
+/// writes to a raw MIDI device, like `hw:1,0,0`. the device is opened on the first write, and
+/// again after a failed one, so unplugging and replugging it picks it back up.
+pub struct AlsaRawOutput {
+    pub device: String,
+    midi: Option<Rawmidi>,
+}
+
+impl AlsaRawOutput {
+    pub fn new(device: String) -> Self {
+        Self { device, midi: None }
+    }
+}
+
+impl MidiOutput for AlsaRawOutput {
+    fn name(&self) -> String {
+        format!("alsa-raw {}", self.device)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if self.midi.is_none() {
+            match Rawmidi::new(&self.device, Direction::Playback, false) {
+                Ok(midi) => self.midi = Some(midi),
+                Err(e) => {
+                    log::error!("opening {} for MIDI out failed: {e}", self.device);
+                    return;
+                }
+            }
+        }
+
+        if let Some(midi) = self.midi.as_ref() {
+            if let Err(e) = midi.io().write_all(bytes) {
+                log::error!("writing to {} failed: {e}", self.device);
+                self.midi = None;
+            }
+        }
+    }
+}
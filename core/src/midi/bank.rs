@@ -0,0 +1,45 @@
+//! bank select. CC 0 (MSB) and CC 32 (LSB) pick the bank on a channel, which the next program
+//! change on it recalls from. banks count from 0, as MSB * 128 + LSB.
+
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+pub const BANK_MSB_CC: u8 = 0;
+pub const BANK_LSB_CC: u8 = 32;
+
+lazy_static! {
+    /// the bank selected on each channel.
+    static ref BANKS: Mutex<[u16; 16]> = Mutex::new([0; 16]);
+}
+
+/// feeds a CC on `channel` (1 through 16) to the bank select state. returns true if it was bank
+/// select and so shouldn't go anywhere else.
+pub fn control(channel: u8, control: u8, value: u8) -> bool {
+    if control != BANK_MSB_CC && control != BANK_LSB_CC {
+        return false;
+    }
+
+    let Ok(mut banks) = BANKS.lock() else {
+        return true;
+    };
+
+    if let Some(bank) = banks.get_mut(channel.wrapping_sub(1) as usize) {
+        let value = (value & 0x7f) as u16;
+
+        *bank = match control {
+            BANK_MSB_CC => value << 7 | *bank & 0x7f,
+            _ => *bank & !0x7f | value,
+        };
+    }
+
+    true
+}
+
+/// the bank selected on `channel`, 1 through 16.
+pub fn bank(channel: u8) -> u16 {
+    BANKS
+        .lock()
+        .ok()
+        .and_then(|banks| banks.get(channel.wrapping_sub(1) as usize).copied())
+        .unwrap_or(0)
+}
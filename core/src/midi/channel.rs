@@ -0,0 +1,278 @@
+//! which MIDI channels get through to the synth, both for the synth as a whole and per input
+//! device, so controllers sharing a hub can be kept apart.
+
+use midi_control::{Channel, MidiMessage};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiveChannel {
+    /// every channel.
+    #[default]
+    Omni,
+    /// only this channel, 1 through 16.
+    Channel(u8),
+}
+
+impl ReceiveChannel {
+    /// omni followed by channels 1 to 16, for pick lists.
+    pub const ALL: [ReceiveChannel; 17] = {
+        let mut all = [ReceiveChannel::Omni; 17];
+        let mut i = 1;
+
+        while i < 17 {
+            all[i] = ReceiveChannel::Channel(i as u8);
+            i += 1;
+        }
+
+        all
+    };
+
+    /// `channel` is 1 through 16.
+    pub fn accepts(&self, channel: u8) -> bool {
+        match self {
+            Self::Omni => true,
+            Self::Channel(ch) => *ch == channel,
+        }
+    }
+}
+
+impl fmt::Display for ReceiveChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Omni => write!(f, "Omni"),
+            Self::Channel(ch) => write!(f, "Ch {ch}"),
+        }
+    }
+}
+
+/// the channel number (1 through 16) of a `Channel`.
+pub fn channel_number(channel: Channel) -> u8 {
+    match channel {
+        Channel::Ch1 => 1,
+        Channel::Ch2 => 2,
+        Channel::Ch3 => 3,
+        Channel::Ch4 => 4,
+        Channel::Ch5 => 5,
+        Channel::Ch6 => 6,
+        Channel::Ch7 => 7,
+        Channel::Ch8 => 8,
+        Channel::Ch9 => 9,
+        Channel::Ch10 => 10,
+        Channel::Ch11 => 11,
+        Channel::Ch12 => 12,
+        Channel::Ch13 => 13,
+        Channel::Ch14 => 14,
+        Channel::Ch15 => 15,
+        Channel::Ch16 => 16,
+    }
+}
+
+/// the channel (1 through 16) a channel voice message is on, `None` for system messages.
+pub fn channel_of(msg: &MidiMessage) -> Option<u8> {
+    match *msg {
+        MidiMessage::NoteOn(ch, _)
+        | MidiMessage::NoteOff(ch, _)
+        | MidiMessage::PolyKeyPressure(ch, _)
+        | MidiMessage::ControlChange(ch, _)
+        | MidiMessage::ProgramChange(ch, _)
+        | MidiMessage::ChannelPressure(ch, _)
+        | MidiMessage::PitchBend(ch, _, _) => Some(channel_number(ch)),
+        _ => None,
+    }
+}
+
+/// the synth's receive channel plus a filter per input device, keyed by source name. devices
+/// can also be turned off altogether.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelFilter {
+    pub receive_channel: ReceiveChannel,
+    /// devices listed here listen on their own channel instead of `receive_channel`.
+    pub devices: BTreeMap<String, ReceiveChannel>,
+    /// devices whose messages are all dropped.
+    pub disabled: BTreeSet<String>,
+}
+
+impl ChannelFilter {
+    /// the filter for `source`.
+    pub fn device(&self, source: &str) -> ReceiveChannel {
+        self.devices.get(source).copied().unwrap_or_default()
+    }
+
+    pub fn set_device(&mut self, source: &str, channel: ReceiveChannel) {
+        if channel == ReceiveChannel::Omni {
+            self.devices.remove(source);
+        } else {
+            self.devices.insert(source.to_string(), channel);
+        }
+    }
+
+    pub fn enabled(&self, source: &str) -> bool {
+        !self.disabled.contains(source)
+    }
+
+    pub fn set_enabled(&mut self, source: &str, enabled: bool) {
+        if enabled {
+            self.disabled.remove(source);
+        } else {
+            self.disabled.insert(source.to_string());
+        }
+    }
+
+    /// the channel `source` plays the synth on: its own if it has one, the receive channel
+    /// otherwise.
+    pub fn listens_on(&self, source: &str) -> ReceiveChannel {
+        self.devices
+            .get(source)
+            .copied()
+            .unwrap_or(self.receive_channel)
+    }
+
+    /// true if `msg` from `source` should reach the synth. system messages always do, unless the
+    /// device is turned off.
+    pub fn accepts(&self, source: &str, msg: &MidiMessage) -> bool {
+        if !self.enabled(source) {
+            return false;
+        }
+
+        match channel_of(msg) {
+            Some(channel) => self.listens_on(source).accepts(channel),
+            None => true,
+        }
+    }
+
+    /// like `accepts` but ignoring the receive channel, for multi-timbral mode where the parts
+    /// pick their own channels.
+    pub fn device_accepts(&self, source: &str, msg: &MidiMessage) -> bool {
+        if !self.enabled(source) {
+            return false;
+        }
+
+        match channel_of(msg) {
+            Some(channel) => self.device(source).accepts(channel),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midi_control::{ControlEvent, KeyEvent};
+
+    const KEYS: &str = "keys";
+    const PADS: &str = "pads";
+
+    fn note(channel: Channel) -> MidiMessage {
+        MidiMessage::NoteOn(
+            channel,
+            KeyEvent {
+                key: 60,
+                value: 100,
+            },
+        )
+    }
+
+    fn cc(channel: Channel) -> MidiMessage {
+        MidiMessage::ControlChange(
+            channel,
+            ControlEvent {
+                control: 1,
+                value: 64,
+            },
+        )
+    }
+
+    #[test]
+    fn omni() {
+        let filter = ChannelFilter::default();
+
+        assert!(filter.accepts(KEYS, &note(Channel::Ch1)));
+        assert!(filter.accepts(KEYS, &note(Channel::Ch10)));
+        assert!(filter.accepts(PADS, &cc(Channel::Ch16)));
+    }
+
+    #[test]
+    fn receive_channel() {
+        let filter = ChannelFilter {
+            receive_channel: ReceiveChannel::Channel(3),
+            ..ChannelFilter::default()
+        };
+
+        assert!(filter.accepts(KEYS, &note(Channel::Ch3)));
+        assert!(filter.accepts(KEYS, &cc(Channel::Ch3)));
+        assert!(!filter.accepts(KEYS, &note(Channel::Ch1)));
+        assert!(!filter.accepts(KEYS, &note(Channel::Ch4)));
+    }
+
+    #[test]
+    fn device_beats_receive_channel() {
+        let mut filter = ChannelFilter {
+            receive_channel: ReceiveChannel::Channel(1),
+            ..ChannelFilter::default()
+        };
+        filter.set_device(PADS, ReceiveChannel::Channel(10));
+
+        assert!(filter.accepts(PADS, &note(Channel::Ch10)));
+        assert!(!filter.accepts(PADS, &note(Channel::Ch1)));
+        assert!(filter.accepts(KEYS, &note(Channel::Ch1)));
+        assert!(!filter.accepts(KEYS, &note(Channel::Ch10)));
+
+        assert!(filter.device_accepts(PADS, &note(Channel::Ch10)));
+        assert!(!filter.device_accepts(PADS, &note(Channel::Ch1)));
+        assert!(filter.device_accepts(KEYS, &note(Channel::Ch10)));
+
+        // setting it back to omni hands it back to the receive channel.
+        filter.set_device(PADS, ReceiveChannel::Omni);
+        assert!(filter.devices.is_empty());
+        assert_eq!(filter.listens_on(PADS), ReceiveChannel::Channel(1));
+        assert!(!filter.accepts(PADS, &note(Channel::Ch10)));
+    }
+
+    #[test]
+    fn system_messages_pass() {
+        let mut filter = ChannelFilter {
+            receive_channel: ReceiveChannel::Channel(5),
+            ..ChannelFilter::default()
+        };
+        filter.set_device(PADS, ReceiveChannel::Channel(10));
+
+        for msg in [
+            MidiMessage::from([0xf8].as_slice()),
+            MidiMessage::from([0xfa].as_slice()),
+            MidiMessage::from([0xf2, 0x10, 0x00].as_slice()),
+        ] {
+            assert_eq!(channel_of(&msg), None);
+            assert!(filter.accepts(KEYS, &msg));
+            assert!(filter.accepts(PADS, &msg));
+            assert!(filter.device_accepts(PADS, &msg));
+        }
+    }
+
+    #[test]
+    fn disabled() {
+        let mut filter = ChannelFilter::default();
+        filter.set_enabled(KEYS, false);
+
+        assert!(!filter.accepts(KEYS, &note(Channel::Ch1)));
+        assert!(!filter.accepts(KEYS, &MidiMessage::from([0xf8].as_slice())));
+        assert!(!filter.device_accepts(KEYS, &note(Channel::Ch1)));
+        assert!(filter.accepts(PADS, &note(Channel::Ch1)));
+
+        filter.set_enabled(KEYS, true);
+        assert!(filter.disabled.is_empty());
+        assert!(filter.accepts(KEYS, &note(Channel::Ch1)));
+    }
+
+    #[test]
+    fn all_channels() {
+        assert_eq!(ReceiveChannel::ALL[0], ReceiveChannel::Omni);
+
+        for (i, channel) in ReceiveChannel::ALL[1..].iter().enumerate() {
+            assert_eq!(*channel, ReceiveChannel::Channel(i as u8 + 1));
+        }
+    }
+}
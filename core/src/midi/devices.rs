@@ -0,0 +1,99 @@
+//! the MIDI devices the platform has told us about. platforms with hotplug (android) add and
+//! remove devices as they come and go, and every change is passed on to the UI as a `UserEvent`.
+//! unplugged devices stay listed as disconnected, so their settings can still be seen.
+
+use crate::UserEvent;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, RwLock};
+use winit::event_loop::EventLoopProxy;
+
+/// the platform's id for a device, only good while it is plugged in.
+pub type DeviceId = i32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MidiDevice {
+    pub id: DeviceId,
+    pub manufacturer: String,
+    pub name: String,
+    /// ports the device takes MIDI on.
+    pub inputs: u32,
+    /// ports the device sends MIDI on. a device without any can't play the synth.
+    pub outputs: u32,
+    pub connected: bool,
+}
+
+impl MidiDevice {
+    /// the source name its messages are tagged with, and its settings are kept under. ids
+    /// change from one connection to the next, so this is what a device is remembered by.
+    pub fn source_name(&self) -> String {
+        match (self.manufacturer.trim(), self.name.trim()) {
+            ("", name) => name.to_string(),
+            (manufacturer, name) if name.starts_with(manufacturer) => name.to_string(),
+            (manufacturer, name) => format!("{manufacturer} {name}"),
+        }
+    }
+}
+
+lazy_static! {
+    static ref DEVICES: RwLock<BTreeMap<DeviceId, MidiDevice>> = RwLock::new(BTreeMap::new());
+    /// where changes are announced, set once the event loop is up.
+    static ref LISTENER: Mutex<Option<EventLoopProxy<UserEvent>>> = Mutex::new(None);
+}
+
+/// sends every change from now on to `proxy`.
+pub fn listen(proxy: EventLoopProxy<UserEvent>) {
+    if let Ok(mut listener) = LISTENER.lock() {
+        *listener = Some(proxy);
+    }
+}
+
+fn notify(event: UserEvent) {
+    if let Some(proxy) = LISTENER.lock().ok().and_then(|listener| listener.clone()) {
+        let _ = proxy.send_event(event);
+    }
+}
+
+/// a device was plugged in. replaces what is known about it, and any disconnected entry it left
+/// behind last time.
+pub fn add(mut device: MidiDevice) {
+    device.connected = true;
+    log::info!("MIDI device connected: {}", device.source_name());
+
+    if let Ok(mut devices) = DEVICES.write() {
+        let name = device.source_name();
+        devices.retain(|_, known| known.connected || known.source_name() != name);
+        devices.insert(device.id, device.clone());
+    }
+
+    notify(UserEvent::MidiDeviceAdded(device));
+}
+
+/// a device was unplugged.
+pub fn remove(id: DeviceId) {
+    let Ok(mut devices) = DEVICES.write() else {
+        return;
+    };
+
+    let Some(device) = devices.get_mut(&id) else {
+        return;
+    };
+
+    device.connected = false;
+    log::info!("MIDI device disconnected: {}", device.source_name());
+    drop(devices);
+
+    notify(UserEvent::MidiDeviceRemoved(id));
+}
+
+pub fn get(id: DeviceId) -> Option<MidiDevice> {
+    DEVICES.read().ok()?.get(&id).cloned()
+}
+
+/// every device seen so far, connected or not.
+pub fn devices() -> Vec<MidiDevice> {
+    DEVICES
+        .read()
+        .map(|devices| devices.values().cloned().collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,334 @@
+//! the CC mapping table and MIDI learn. each binding ties a controller number to one of the
+//! synth's parameters, with its own range, curve, and direction. the table lives in the config
+//! so it survives restarts.
+
+use crate::synth::StereoParam;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// the parameter waiting for a controller to be moved, set from the UI and taken by the
+    /// router when the next CC comes in.
+    static ref LEARNING: Mutex<Option<Target>> = Mutex::new(None);
+}
+
+/// a parameter a controller can drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// one of the engine's eight knobs, 1 through 8.
+    Knob(u8),
+    VolumeSwell,
+    Pan,
+    Width,
+    Spread,
+}
+
+impl Target {
+    /// every target, for pick lists.
+    pub const ALL: [Target; 12] = [
+        Target::Knob(1),
+        Target::Knob(2),
+        Target::Knob(3),
+        Target::Knob(4),
+        Target::Knob(5),
+        Target::Knob(6),
+        Target::Knob(7),
+        Target::Knob(8),
+        Target::VolumeSwell,
+        Target::Pan,
+        Target::Width,
+        Target::Spread,
+    ];
+
+    /// the target a stereo change is for and its value as 0 to 1. `None` for unison, which is
+    /// a switch.
+    pub fn from_stereo(param: StereoParam) -> Option<(Target, f32)> {
+        match param {
+            StereoParam::Pan(pan) => Some((Target::Pan, (pan + 1.0) * 0.5)),
+            StereoParam::Width(width) => Some((Target::Width, width * 0.5)),
+            StereoParam::Spread(spread) => Some((Target::Spread, spread)),
+            StereoParam::Unison(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Knob(knob) => write!(f, "Knob {knob}"),
+            Self::VolumeSwell => write!(f, "Volume swell"),
+            Self::Pan => write!(f, "Pan"),
+            Self::Width => write!(f, "Width"),
+            Self::Spread => write!(f, "Spread"),
+        }
+    }
+}
+
+/// how the controller's travel is shaped before it is scaled into the binding's range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// slow at the bottom, fast at the top.
+    Exponential,
+    /// fast at the bottom, slow at the top.
+    Logarithmic,
+}
+
+impl Curve {
+    pub const ALL: [Curve; 3] = [Curve::Linear, Curve::Exponential, Curve::Logarithmic];
+
+    /// `x` is 0 to 1.
+    fn shape(&self, x: f32) -> f32 {
+        match self {
+            Self::Linear => x,
+            Self::Exponential => x * x,
+            Self::Logarithmic => x.sqrt(),
+        }
+    }
+
+    /// undoes `shape`.
+    fn unshape(&self, y: f32) -> f32 {
+        match self {
+            Self::Linear => y,
+            Self::Exponential => y.sqrt(),
+            Self::Logarithmic => y * y,
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "Linear"),
+            Self::Exponential => write!(f, "Exponential"),
+            Self::Logarithmic => write!(f, "Logarithmic"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub control: u8,
+    pub target: Target,
+    /// what the bottom and top of the controller's travel map to, out of the target's 0 to 1.
+    /// `min` above `max` is allowed and runs the other way.
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default = "one")]
+    pub max: f32,
+    #[serde(default)]
+    pub curve: Curve,
+    #[serde(default)]
+    pub invert: bool,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl Binding {
+    /// a full range, linear binding.
+    pub fn new(control: u8, target: Target) -> Self {
+        Self {
+            control,
+            target,
+            min: 0.0,
+            max: 1.0,
+            curve: Curve::Linear,
+            invert: false,
+        }
+    }
+
+    /// maps a 7 bit controller value to the target's 0 to 1.
+    pub fn scale(&self, value: u8) -> f32 {
+        let x = (value.min(127) as f32) / 127.0;
+        let x = if self.invert { 1.0 - x } else { x };
+
+        self.min + (self.max - self.min) * self.curve.shape(x)
+    }
+
+    /// the controller value that `scale`s to `value`, or the closest the range allows. for
+    /// sending a parameter back out.
+    pub fn unscale(&self, value: f32) -> u8 {
+        let span = self.max - self.min;
+        let y = if span == 0.0 {
+            0.0
+        } else {
+            ((value - self.min) / span).clamp(0.0, 1.0)
+        };
+        let x = self.curve.unshape(y);
+        let x = if self.invert { 1.0 - x } else { x };
+
+        (x * 127.0).round() as u8
+    }
+}
+
+/// the bindings, in the order they were made. one controller can drive several targets, but a
+/// target only listens to one controller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MidiMap {
+    pub bindings: Vec<Binding>,
+}
+
+impl Default for MidiMap {
+    /// CCs 70 to 77 on the knobs and the mod wheel on the volume swell.
+    fn default() -> Self {
+        let mut bindings: Vec<Binding> = (1..=8)
+            .map(|knob| Binding::new(69 + knob, Target::Knob(knob)))
+            .collect();
+        bindings.push(Binding::new(1, Target::VolumeSwell));
+
+        Self { bindings }
+    }
+}
+
+impl MidiMap {
+    /// the bindings listening to `control`.
+    pub fn bindings_for(&self, control: u8) -> impl Iterator<Item = &Binding> {
+        self.bindings.iter().filter(move |b| b.control == control)
+    }
+
+    /// points `target` at `control`. an existing binding for the target keeps its range and
+    /// curve, otherwise a new full range one is made.
+    pub fn bind(&mut self, control: u8, target: Target) {
+        match self.bindings.iter_mut().find(|b| b.target == target) {
+            Some(binding) => binding.control = control,
+            None => self.bindings.push(Binding::new(control, target)),
+        }
+    }
+}
+
+/// waits for the next CC to bind to `target`, replacing anything already waiting.
+pub fn learn(target: Target) {
+    if let Ok(mut learning) = LEARNING.lock() {
+        *learning = Some(target);
+    }
+}
+
+pub fn cancel_learn() {
+    if let Ok(mut learning) = LEARNING.lock() {
+        *learning = None;
+    }
+}
+
+/// the target waiting to be bound, if any.
+pub fn learning() -> Option<Target> {
+    LEARNING.lock().ok().and_then(|learning| *learning)
+}
+
+/// takes the waiting target, so only one CC gets bound to it.
+pub fn take_learning() -> Option<Target> {
+    LEARNING
+        .lock()
+        .ok()
+        .and_then(|mut learning| learning.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(min: f32, max: f32, curve: Curve, invert: bool) -> Binding {
+        Binding {
+            min,
+            max,
+            curve,
+            invert,
+            ..Binding::new(1, Target::VolumeSwell)
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for curve in Curve::ALL {
+            for invert in [false, true] {
+                for (min, max) in [(0.0, 1.0), (0.2, 0.6), (1.0, 0.0), (0.9, 0.3)] {
+                    let binding = binding(min, max, curve, invert);
+
+                    for value in 0..=127 {
+                        let scaled = binding.scale(value);
+                        assert!(scaled >= min.min(max) - 1.0e-6);
+                        assert!(scaled <= min.max(max) + 1.0e-6);
+                        assert_eq!(binding.unscale(scaled), value, "{binding:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ends() {
+        for curve in Curve::ALL {
+            let binding = binding(0.25, 0.75, curve, false);
+            assert_eq!(binding.scale(0), 0.25);
+            assert_eq!(binding.scale(127), 0.75);
+
+            let inverted = binding(0.25, 0.75, curve, true);
+            assert_eq!(inverted.scale(0), 0.75);
+            assert_eq!(inverted.scale(127), 0.25);
+
+            // values the range can't reach go to the closest end.
+            assert_eq!(binding.unscale(0.0), 0);
+            assert_eq!(binding.unscale(1.0), 127);
+            assert_eq!(inverted.unscale(0.0), 127);
+            assert_eq!(inverted.unscale(1.0), 0);
+        }
+    }
+
+    #[test]
+    fn curves() {
+        let exponential = binding(0.0, 1.0, Curve::Exponential, false);
+        let logarithmic = binding(0.0, 1.0, Curve::Logarithmic, false);
+        let linear = binding(0.0, 1.0, Curve::Linear, false);
+
+        assert!(exponential.scale(64) < linear.scale(64));
+        assert!(logarithmic.scale(64) > linear.scale(64));
+        assert_eq!(linear.scale(127), 1.0);
+        assert_eq!(linear.scale(255), 1.0);
+    }
+
+    #[test]
+    fn empty_range() {
+        for curve in Curve::ALL {
+            for invert in [false, true] {
+                let binding = binding(0.5, 0.5, curve, invert);
+
+                for value in [0, 64, 127] {
+                    assert_eq!(binding.scale(value), 0.5);
+                }
+
+                let bottom = if invert { 127 } else { 0 };
+
+                for value in [0.0, 0.5, 1.0] {
+                    assert_eq!(binding.unscale(value), bottom);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bind_keeps_range() {
+        let mut map = MidiMap::default();
+        let swell = map
+            .bindings
+            .iter_mut()
+            .find(|b| b.target == Target::VolumeSwell)
+            .unwrap();
+        swell.min = 0.5;
+        swell.curve = Curve::Exponential;
+
+        map.bind(11, Target::VolumeSwell);
+        let swell: Vec<&Binding> = map.bindings_for(11).collect();
+
+        assert_eq!(swell.len(), 1);
+        assert_eq!(swell[0].min, 0.5);
+        assert_eq!(swell[0].curve, Curve::Exponential);
+        assert_eq!(map.bindings_for(1).count(), 0);
+    }
+}
@@ -0,0 +1,556 @@
+use crate::config::{save_config, CONFIG};
+use crate::synth::bend;
+use crate::synth::stereo::{PAN_CC, SPREAD_CC, WIDTH_CC};
+use crate::synth::{BendRange, StereoParam, SynthCmd, TabSynth, MAX_PARTS};
+use crate::transport;
+use crate::MIDI_RECV;
+use channel::{channel_number, channel_of, ReceiveChannel};
+use learn::Target;
+use log::{debug, error, info};
+use midi_control::{ControlEvent, KeyEvent, MidiMessage};
+use monitor::Logged;
+use mpe::{MpeConfig, Role};
+use rpn::Rpn;
+use std::sync::{Arc, RwLock};
+use std::thread::{spawn, JoinHandle};
+use std::time::Instant;
+use stepper_synth_backend::pygame_coms::SynthEngineType;
+use zones::ZoneConfig;
+
+#[cfg(all(feature = "alsa", target_os = "linux"))]
+pub mod alsa;
+pub mod bank;
+pub mod channel;
+pub mod devices;
+pub mod learn;
+pub mod monitor;
+pub mod mpe;
+pub mod output;
+pub mod parser;
+pub mod rpn;
+pub mod source;
+pub mod zones;
+
+/// a message and the source it came from.
+#[derive(Debug, Clone)]
+pub struct MidiEvent {
+    /// the `MidiSource::name` of the source.
+    pub source: Arc<str>,
+    pub msg: MidiMessage,
+    /// the bytes it arrived as, for the monitor. empty from sources that get messages already
+    /// parsed.
+    pub raw: Vec<u8>,
+    /// when the message arrived, as close to the device as the source can tell.
+    pub time: Instant,
+}
+
+/// clock and transport messages, which `MidiMessage` has no room for. they go straight to the
+/// transport rather than through the router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemMessage {
+    /// 24 of these to a quarter note.
+    Clock,
+    Start,
+    Continue,
+    Stop,
+    /// where to continue from, in sixteenth notes from the start of the song.
+    SongPosition(u16),
+}
+
+impl SystemMessage {
+    /// the message a realtime status byte (`0xf8` and up) stands for, if it is one we use.
+    pub fn realtime(byte: u8) -> Option<Self> {
+        match byte {
+            0xf8 => Some(Self::Clock),
+            0xfa => Some(Self::Start),
+            0xfb => Some(Self::Continue),
+            0xfc => Some(Self::Stop),
+            _ => None,
+        }
+    }
+
+    /// the bytes to send it as.
+    pub fn encode(&self) -> Vec<u8> {
+        match *self {
+            Self::Clock => vec![0xf8],
+            Self::Start => vec![0xfa],
+            Self::Continue => vec![0xfb],
+            Self::Stop => vec![0xfc],
+            Self::SongPosition(beats) => {
+                vec![0xf2, (beats & 0x7f) as u8, (beats >> 7 & 0x7f) as u8]
+            }
+        }
+    }
+}
+
+/// hands a clock or transport message from `source` to the transport, unless the device is
+/// turned off.
+pub fn system(source: &str, msg: SystemMessage, time: Instant) {
+    let enabled = CONFIG
+        .read()
+        .map(|config| config.midi.enabled(source))
+        .unwrap_or(true);
+
+    monitor::record(
+        source.into(),
+        Logged::System(msg),
+        msg.encode(),
+        time,
+        enabled,
+    );
+
+    if enabled {
+        transport::receive(msg, time);
+    }
+}
+
+/// spawns the thread that pulls messages off of `MIDI_RECV`, logs them for the monitor, drops the
+/// ones from devices that are turned off, passes the rest through to the MIDI outputs if thru is
+/// on, drops the ones the channel filters don't let through, and plays what is left on the
+/// synth. in multi-timbral mode the parts' channels stand in for the receive channel.
+pub fn spawn_router(synth: Arc<RwLock<TabSynth>>) -> JoinHandle<()> {
+    spawn(move || {
+        while let Ok(event) = MIDI_RECV.recv() {
+            let (enabled, accepted) = CONFIG
+                .read()
+                .map(|config| {
+                    let accepted = match config.multi.enabled {
+                        true => {
+                            config.midi.device_accepts(&event.source, &event.msg)
+                                && config.multi.covers(&event.msg)
+                        }
+                        false => config.midi.accepts(&event.source, &event.msg),
+                    };
+
+                    // zones and MPE take channels of their own, but still only from the
+                    // devices whose filters let those channels through.
+                    let device_accepts = config.midi.device_accepts(&event.source, &event.msg);
+                    let zoned =
+                        channel_of(&event.msg).is_some_and(|channel| config.zones.covers(channel));
+                    let mpe = config.mpe.covers(&event.msg);
+
+                    (
+                        config.midi.enabled(&event.source),
+                        accepted || (device_accepts && (zoned || mpe)),
+                    )
+                })
+                .unwrap_or((true, true));
+
+            monitor::record(
+                event.source.clone(),
+                Logged::Midi(event.msg.clone()),
+                event.raw.clone(),
+                event.time,
+                enabled && accepted,
+            );
+
+            if !enabled {
+                continue;
+            }
+
+            output::thru(&event.msg);
+
+            if !accepted {
+                continue;
+            }
+
+            if let Ok(ref mut tab_synth) = synth.write() {
+                tab_synth.set_timestamp(Some(event.time));
+                route(tab_synth, &event.msg);
+                tab_synth.set_timestamp(None);
+            }
+        }
+    })
+}
+
+/// turns a single MIDI message into commands for the synth. in multi-timbral mode it plays on
+/// every part on its channel, otherwise on the first part. notes in a keyboard zone play on the
+/// zone's part instead, and the rest of the channel reaches that part too. MPE member channels
+/// always play on the first part, which has the member engines.
+pub fn route(synth: &mut TabSynth, msg: &MidiMessage) {
+    let channel = channel_of(msg);
+    let (mpe, multi, zones) = CONFIG
+        .read()
+        .map(|config| {
+            let parts = channel.and_then(|channel| config.multi.parts_on(channel));
+            let zones = channel
+                .filter(|channel| config.zones.covers(*channel))
+                .map(|_| config.zones.clone());
+            (config.mpe, parts, zones)
+        })
+        .unwrap_or_default();
+
+    // a bit per part.
+    let mut parts = multi.unwrap_or(1);
+
+    if let Some(channel) = channel {
+        if mpe.role(channel) == Role::Member {
+            synth.set_part(Some(0));
+            member(synth, channel, msg, &mpe);
+            return synth.set_part(None);
+        }
+
+        if zone_note(synth, channel, msg, zones.as_ref()) {
+            return;
+        }
+
+        parts |= zones.map_or(0, |zones| zones.parts_on(channel));
+    }
+
+    for part in (0..MAX_PARTS).filter(|part| parts & 1 << part != 0) {
+        synth.set_part(Some(part));
+        play(synth, msg);
+    }
+
+    synth.set_part(None);
+}
+
+/// plays `msg` on the part the synth is sending to.
+fn play(synth: &mut TabSynth, msg: &MidiMessage) {
+    match *msg {
+        MidiMessage::Invalid => {
+            error!("system received an invalid MIDI message.");
+        }
+        MidiMessage::NoteOn(_, KeyEvent { key, value }) => {
+            debug!("playing note: {key}");
+            synth.send(SynthCmd::NoteOn {
+                key,
+                velocity: value,
+            })
+        }
+        MidiMessage::NoteOff(_, KeyEvent { key, value: _ }) => {
+            synth.send(SynthCmd::NoteOff { key })
+        }
+        MidiMessage::PitchBend(_, lsb, msb) => {
+            let deadzone = CONFIG
+                .read()
+                .map(|config| config.bend.deadzone)
+                .unwrap_or_default();
+            let bend = bend::apply_deadzone(bend::decode(lsb, msb), deadzone);
+
+            if bend == 0.0 {
+                synth.send(SynthCmd::Unbend);
+            } else {
+                synth.send(SynthCmd::Bend(bend));
+            }
+        }
+        MidiMessage::ChannelPressure(_, value) => {
+            synth.send(SynthCmd::Pressure(value as f32 / 127.0))
+        }
+        MidiMessage::PolyKeyPressure(_, KeyEvent { key, value }) => {
+            synth.send(SynthCmd::PolyPressure {
+                key,
+                value: value as f32 / 127.0,
+            })
+        }
+        MidiMessage::ProgramChange(ch, program) => {
+            let bank = bank::bank(channel_number(ch));
+            info!("program change: {program} in bank {bank}");
+            recall(synth, bank, program);
+        }
+        MidiMessage::ControlChange(ch, ControlEvent { control, .. })
+            if control >= CHANNEL_MODE_CC =>
+        {
+            channel_mode(synth, channel_number(ch), control, msg);
+        }
+        MidiMessage::ControlChange(
+            ch,
+            ControlEvent {
+                control,
+                value: raw,
+            },
+        ) => {
+            if let Some(target) = learn::take_learning() {
+                info!("binding CC {control} to {target}");
+                save_config(|config| config.mappings.bind(control, target));
+
+                return;
+            }
+
+            // the user's bindings win over everything else, including the fixed controllers
+            // below.
+            let targets: Vec<(Target, f32)> = CONFIG
+                .read()
+                .map(|config| {
+                    config
+                        .mappings
+                        .bindings_for(control)
+                        .map(|binding| (binding.target, binding.scale(raw)))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if !targets.is_empty() {
+                for (target, value) in targets {
+                    control_target(synth, target, value);
+                }
+
+                return;
+            }
+
+            if bank::control(channel_number(ch), control, raw) {
+                return;
+            }
+
+            match rpn::control(channel_number(ch), control, raw, synth.bend_range()) {
+                Some(Rpn::BendRange(range)) => {
+                    info!("bend range set to {range:?} by RPN 0");
+                    return synth.set_bend_range(range);
+                }
+                Some(Rpn::MpeConfiguration(members)) => {
+                    return configure_mpe(synth, channel_number(ch), members);
+                }
+                None => {}
+            }
+
+            let value = raw as f32 / 127.0;
+
+            // the stereo controls and pedals belong to the host, not the engine, so they're
+            // handled the same whichever engine is loaded.
+            match control {
+                SUSTAIN_CC => return synth.send(SynthCmd::Sustain(value >= 0.5)),
+                SOSTENUTO_CC => return synth.send(SynthCmd::Sostenuto(value >= 0.5)),
+                SOFT_CC => return synth.send(SynthCmd::Soft(value >= 0.5)),
+                PAN_CC => return synth.set_stereo(StereoParam::Pan(value * 2.0 - 1.0)),
+                WIDTH_CC => return synth.set_stereo(StereoParam::Width(value * 2.0)),
+                SPREAD_CC => return synth.set_stereo(StereoParam::Spread(value)),
+                _ => {}
+            }
+
+            // anything unbound goes to the wavetable engine as is, it has its own CC handling.
+            if matches!(synth.engine_type(), SynthEngineType::WaveTable) {
+                synth.send(SynthCmd::Midi(msg.clone()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// plays a note through the keyboard zones, see `zones`. `zones` is `None` when none are on
+/// `channel`, but notes they are still sounding from before get their note off. returns false
+/// if the message was left for the usual routing.
+fn zone_note(
+    synth: &mut TabSynth,
+    channel: u8,
+    msg: &MidiMessage,
+    zones: Option<&ZoneConfig>,
+) -> bool {
+    let notes: Vec<(usize, SynthCmd)> = match *msg {
+        MidiMessage::NoteOn(_, KeyEvent { key, value }) if value > 0 => {
+            let Some(zones) = zones else {
+                return false;
+            };
+
+            // a retrigger may land in other zones than the note it replaces did.
+            let off = zones::note_off(channel, key)
+                .into_iter()
+                .map(|note| (note.part, SynthCmd::NoteOff { key: note.played }));
+
+            let on = zones::note_on(zones, channel, key, value)
+                .into_iter()
+                .map(|note| {
+                    let cmd = SynthCmd::NoteOn {
+                        key: note.played,
+                        velocity: value,
+                    };
+                    (note.part, cmd)
+                });
+
+            off.chain(on).collect()
+        }
+        MidiMessage::NoteOn(_, KeyEvent { key, .. })
+        | MidiMessage::NoteOff(_, KeyEvent { key, .. }) => zones::note_off(channel, key)
+            .into_iter()
+            .map(|note| (note.part, SynthCmd::NoteOff { key: note.played }))
+            .collect(),
+        MidiMessage::PolyKeyPressure(_, KeyEvent { key, value }) => zones::sounding(channel, key)
+            .into_iter()
+            .map(|note| {
+                let cmd = SynthCmd::PolyPressure {
+                    key: note.played,
+                    value: value as f32 / 127.0,
+                };
+                (note.part, cmd)
+            })
+            .collect(),
+        _ => return false,
+    };
+
+    // a note the zones never played is left to the channel's parts, unless zones own the
+    // channel now.
+    if notes.is_empty() && zones.is_none() {
+        return false;
+    }
+
+    for (part, cmd) in notes {
+        synth.set_part(Some(part));
+        synth.send(cmd);
+    }
+
+    synth.set_part(None);
+
+    true
+}
+
+/// plays a message from an MPE member channel on that channel's own engine. the master channel
+/// goes through `route` as usual, so its wheel and pedals reach every note in the zone.
+fn member(synth: &mut TabSynth, channel: u8, msg: &MidiMessage, config: &MpeConfig) {
+    match *msg {
+        MidiMessage::NoteOn(_, KeyEvent { key, value }) => synth.send(SynthCmd::MemberNoteOn {
+            channel,
+            key,
+            velocity: value,
+        }),
+        MidiMessage::NoteOff(_, KeyEvent { key, .. }) => {
+            synth.send(SynthCmd::MemberNoteOff { channel, key })
+        }
+        // no deadzone, per note bends are slides and vibrato, not a wheel that might not center.
+        MidiMessage::PitchBend(_, lsb, msb) => synth.send(SynthCmd::MemberBend {
+            channel,
+            bend: bend::decode(lsb, msb),
+        }),
+        MidiMessage::ChannelPressure(_, value) => synth.send(SynthCmd::MemberPressure {
+            channel,
+            value: value as f32 / 127.0,
+        }),
+        MidiMessage::ControlChange(
+            _,
+            ControlEvent {
+                control: mpe::TIMBRE_CC,
+                value,
+            },
+        ) => {
+            if config.timbre_knob != 0 {
+                synth.send(SynthCmd::MemberKnob {
+                    channel,
+                    knob: config.timbre_knob,
+                    value: value as f32 / 127.0,
+                });
+            }
+        }
+        MidiMessage::ControlChange(_, ControlEvent { control, value }) => {
+            let range = BendRange::symmetric(config.member_bend);
+
+            // every member channel shares one range, whichever of them set it.
+            if let Some(Rpn::BendRange(range)) = rpn::control(channel, control, value, range) {
+                info!("member bend range set to {range:?} by RPN 0");
+
+                if let Ok(mut config) = CONFIG.write() {
+                    config.mpe.member_bend = range.up;
+                }
+
+                synth.set_member_range(range);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// applies an MPE Configuration Message from `channel`. like omni mode this isn't saved,
+/// controllers send it again when they connect.
+fn configure_mpe(synth: &mut TabSynth, channel: u8, members: u8) {
+    if channel != mpe::LOWER_MASTER && channel != mpe::UPPER_MASTER {
+        return;
+    }
+
+    let Ok(mut config) = CONFIG.write() else {
+        return;
+    };
+
+    let had_zone = config.mpe.has_zone();
+    config.mpe.configure(channel, members);
+    info!("MPE configured from channel {channel}: {:?}", config.mpe);
+    let member_range = BendRange::symmetric(config.mpe.member_bend);
+    // the first part is rebuilt with or without the member engines.
+    let rebuild = (had_zone != config.mpe.has_zone()).then(|| config.clone());
+    drop(config);
+
+    // the zones moved, so notes may be on channels that mean something else now.
+    // the member engines are on the first part.
+    synth.send_to(0, SynthCmd::AllNotesOff);
+
+    if let Some(config) = rebuild {
+        synth.load_parts(&config);
+    }
+
+    synth.set_member_range(member_range);
+    synth.set_bend_range(BendRange::default());
+}
+
+/// switches the part the synth is sending to over to `program` in `bank`, see
+/// `TabSynth::recall`. the part's entry in the config follows along so it is rebuilt the same
+/// way, but like any program change this isn't saved by itself.
+pub fn recall(synth: &mut TabSynth, bank: u16, program: u8) {
+    let Some(engine) = synth.recall(bank, program) else {
+        return;
+    };
+
+    let index = synth.part();
+
+    if let Ok(mut config) = CONFIG.write() {
+        if let Some(part) = config.multi.parts.get_mut(index) {
+            part.engine = engine;
+            part.bank = bank;
+            part.program = program & 0x7f;
+        }
+    }
+}
+
+/// sets `target` to `value`, 0 to 1.
+pub fn control_target(synth: &mut TabSynth, target: Target, value: f32) {
+    match target {
+        Target::Knob(knob) => synth.set_knob(knob, value),
+        Target::VolumeSwell => synth.send(SynthCmd::VolumeSwell(value)),
+        Target::Pan => synth.set_stereo(StereoParam::Pan(value * 2.0 - 1.0)),
+        Target::Width => synth.set_stereo(StereoParam::Width(value * 2.0)),
+        Target::Spread => synth.set_stereo(StereoParam::Spread(value)),
+    }
+}
+
+/// pedal CCs, values of 64 and up are down.
+pub const SUSTAIN_CC: u8 = 64;
+pub const SOSTENUTO_CC: u8 = 66;
+pub const SOFT_CC: u8 = 67;
+
+/// the first of the channel mode messages (CC 120 to 127).
+pub const CHANNEL_MODE_CC: u8 = 120;
+
+/// handles a channel mode message as the MIDI spec lays out. everything from omni off on is also
+/// an All Notes Off.
+fn channel_mode(synth: &mut TabSynth, channel: u8, control: u8, msg: &MidiMessage) {
+    // whatever the zones were sounding is being stopped.
+    if matches!(control, 120 | 123..=125) {
+        zones::clear();
+    }
+
+    match control {
+        120 => synth.send(SynthCmd::AllSoundOff),
+        121 => {
+            synth.send(SynthCmd::ResetControllers);
+            rpn::reset(channel);
+
+            // the wavetable engine keeps its own copy of the controllers it was sent.
+            if matches!(synth.engine_type(), SynthEngineType::WaveTable) {
+                synth.send(SynthCmd::Midi(msg.clone()));
+            }
+        }
+        // there's no built in keyboard to disconnect.
+        122 => debug!("ignoring local control"),
+        123 => synth.send(SynthCmd::AllNotesOff),
+        124 | 125 => {
+            synth.send(SynthCmd::AllNotesOff);
+
+            // omni off narrows to the channel it arrived on. this isn't saved, the configured
+            // receive channel is back after a restart.
+            if let Ok(mut config) = CONFIG.write() {
+                config.midi.receive_channel = match control {
+                    124 => ReceiveChannel::Channel(channel),
+                    _ => ReceiveChannel::Omni,
+                };
+            }
+        }
+        126 => synth.send(SynthCmd::Mono(true)),
+        127 => synth.send(SynthCmd::Mono(false)),
+        _ => {}
+    }
+}
@@ -0,0 +1,231 @@
+//! the MIDI monitor: a log of the last messages from every input, shown on the Monitor screen so
+//! a controller can be checked without a computer. everything the router and the transport get
+//! is logged, including what the device switches and channel filters drop.
+
+use super::channel::{channel_of, ReceiveChannel};
+use super::output;
+use super::SystemMessage;
+use crate::UserEvent;
+use lazy_static::lazy_static;
+use midi_control::MidiMessage;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use winit::event_loop::EventLoopProxy;
+
+/// how many messages are kept, the oldest go first.
+pub const MONITOR_SIZE: usize = 1000;
+
+/// the fastest the screen is asked to redraw while messages stream in.
+const REFRESH: Duration = Duration::from_millis(50);
+
+lazy_static! {
+    static ref LOG: Mutex<Log> = Mutex::new(Log::default());
+    /// where timestamps count from.
+    static ref START: Instant = Instant::now();
+    /// where the screen is told about new messages, set once the event loop is up.
+    static ref LISTENER: Mutex<Option<EventLoopProxy<UserEvent>>> = Mutex::new(None);
+}
+
+/// set while the Monitor screen is open, so nothing is redrawn for it otherwise.
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default)]
+struct Log {
+    entries: VecDeque<Entry>,
+    paused: bool,
+    /// when the screen was last told about a new message.
+    notified: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Logged {
+    Midi(MidiMessage),
+    System(SystemMessage),
+}
+
+/// what sort of message an entry is, for filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Notes,
+    Controllers,
+    Programs,
+    /// bend and pressure.
+    Expression,
+    /// clock, transport, and anything else without a channel.
+    System,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Notes => write!(f, "Notes"),
+            Self::Controllers => write!(f, "CCs"),
+            Self::Programs => write!(f, "Programs"),
+            Self::Expression => write!(f, "Bend/pressure"),
+            Self::System => write!(f, "System"),
+        }
+    }
+}
+
+/// a message as it came in.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub time: Instant,
+    /// the `MidiSource::name` of the source.
+    pub source: Arc<str>,
+    pub message: Logged,
+    /// the bytes it arrived as.
+    pub bytes: Vec<u8>,
+    /// false if the device is turned off or the channel filters dropped it.
+    pub played: bool,
+}
+
+impl Entry {
+    /// seconds since the app started.
+    pub fn seconds(&self) -> f64 {
+        self.time.saturating_duration_since(*START).as_secs_f64()
+    }
+
+    /// 1 through 16, `None` for system messages.
+    pub fn channel(&self) -> Option<u8> {
+        match self.message {
+            Logged::Midi(ref msg) => channel_of(msg),
+            Logged::System(_) => None,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self.message {
+            Logged::Midi(MidiMessage::NoteOn(..) | MidiMessage::NoteOff(..)) => Kind::Notes,
+            Logged::Midi(MidiMessage::ControlChange(..)) => Kind::Controllers,
+            Logged::Midi(MidiMessage::ProgramChange(..)) => Kind::Programs,
+            Logged::Midi(
+                MidiMessage::PitchBend(..)
+                | MidiMessage::ChannelPressure(..)
+                | MidiMessage::PolyKeyPressure(..),
+            ) => Kind::Expression,
+            _ => Kind::System,
+        }
+    }
+}
+
+/// which entries the screen shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Filter {
+    /// `None` shows every kind.
+    pub kind: Option<Kind>,
+    /// anything but omni hides system messages.
+    pub channel: ReceiveChannel,
+    /// clock comes 24 times a beat and buries everything else, so it is hidden unless asked for.
+    pub clock: bool,
+}
+
+impl Filter {
+    pub fn shows(&self, entry: &Entry) -> bool {
+        if matches!(entry.message, Logged::System(SystemMessage::Clock)) && !self.clock {
+            return false;
+        }
+
+        let channel = match entry.channel() {
+            Some(channel) => self.channel.accepts(channel),
+            None => self.channel == ReceiveChannel::Omni,
+        };
+
+        channel && self.kind.is_none_or(|kind| kind == entry.kind())
+    }
+}
+
+/// asks `proxy` to redraw when messages come in while the screen is watching.
+pub fn listen(proxy: EventLoopProxy<UserEvent>) {
+    lazy_static::initialize(&START);
+
+    if let Ok(mut listener) = LISTENER.lock() {
+        *listener = Some(proxy);
+    }
+}
+
+/// called with true while the Monitor screen is open.
+pub fn watch(watching: bool) {
+    WATCHING.store(watching, Ordering::Relaxed);
+}
+
+/// logs a message from `source` and the bytes it arrived as. sources that only had the parsed
+/// message pass no bytes, and get them rebuilt. does nothing while paused.
+pub fn record(source: Arc<str>, message: Logged, bytes: Vec<u8>, time: Instant, played: bool) {
+    let Ok(mut log) = LOG.lock() else {
+        return;
+    };
+
+    if log.paused {
+        return;
+    }
+
+    let bytes = match message {
+        Logged::Midi(ref msg) if bytes.is_empty() => output::encode(msg).unwrap_or_default(),
+        _ => bytes,
+    };
+
+    if log.entries.len() >= MONITOR_SIZE {
+        log.entries.pop_front();
+    }
+
+    log.entries.push_back(Entry {
+        time,
+        source,
+        message,
+        bytes,
+        played,
+    });
+
+    if !WATCHING.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // the once a second tick picks up whatever comes in between.
+    let now = Instant::now();
+
+    if log.notified.is_some_and(|at| now - at < REFRESH) {
+        return;
+    }
+
+    log.notified = Some(now);
+    drop(log);
+
+    if let Some(proxy) = LISTENER.lock().ok().and_then(|listener| listener.clone()) {
+        let _ = proxy.send_event(UserEvent::MidiMonitor);
+    }
+}
+
+/// up to `limit` of the entries `filter` shows, newest first.
+pub fn entries(filter: &Filter, limit: usize) -> Vec<Entry> {
+    LOG.lock()
+        .map(|log| {
+            log.entries
+                .iter()
+                .rev()
+                .filter(|entry| filter.shows(entry))
+                .take(limit)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut log) = LOG.lock() {
+        log.entries.clear();
+    }
+}
+
+pub fn is_paused() -> bool {
+    LOG.lock().is_ok_and(|log| log.paused)
+}
+
+pub fn set_paused(paused: bool) {
+    if let Ok(mut log) = LOG.lock() {
+        log.paused = paused;
+    }
+}
@@ -0,0 +1,247 @@
+//! MIDI Polyphonic Expression. a zone is a master channel plus a run of member channels; the
+//! controller plays every note on a member channel of its own, so that channel's bend, pressure,
+//! and timbre (CC 74) move just that note. the master channel carries what applies to the whole
+//! zone, like sustain and a bend that moves every note. the lower zone is mastered on channel 1
+//! with members counting up from 2, the upper zone on channel 16 with members counting down
+//! from 15.
+
+use super::channel::channel_of;
+use midi_control::MidiMessage;
+use serde::{Deserialize, Serialize};
+
+/// the most member channels one zone can have.
+pub const MAX_MEMBERS: u8 = 15;
+/// the bend range MPE starts member channels on, in semitones.
+pub const DEFAULT_MEMBER_BEND: f32 = 48.0;
+/// the third dimension of MPE, usually sliding a finger up and down the key.
+pub const TIMBRE_CC: u8 = 74;
+
+pub const LOWER_MASTER: u8 = 1;
+pub const UPPER_MASTER: u8 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MpeConfig {
+    pub enabled: bool,
+    /// member channels in the lower zone, 0 for no lower zone.
+    pub lower: u8,
+    /// member channels in the upper zone, 0 for no upper zone.
+    pub upper: u8,
+    /// the bend range of member channels, in semitones. the master channel uses the patch's.
+    pub member_bend: f32,
+    /// the knob timbre drives on each note, 1 through 8, 0 for none.
+    pub timbre_knob: u8,
+}
+
+impl Default for MpeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lower: MAX_MEMBERS,
+            upper: 0,
+            member_bend: DEFAULT_MEMBER_BEND,
+            timbre_knob: 1,
+        }
+    }
+}
+
+/// what a channel is to MPE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Master,
+    Member,
+    /// not in a zone, or MPE is off. played the usual way.
+    Outside,
+}
+
+impl MpeConfig {
+    /// the role of `channel`, 1 through 16.
+    pub fn role(&self, channel: u8) -> Role {
+        if !self.enabled {
+            return Role::Outside;
+        }
+
+        let lower = self.lower.min(MAX_MEMBERS);
+        let upper = self.upper.min(MAX_MEMBERS);
+
+        match channel {
+            LOWER_MASTER if lower > 0 => Role::Master,
+            UPPER_MASTER if upper > 0 => Role::Master,
+            ch if lower > 0 && ch > LOWER_MASTER && ch <= LOWER_MASTER + lower => Role::Member,
+            ch if upper > 0 && ch < UPPER_MASTER && ch >= UPPER_MASTER - upper => Role::Member,
+            _ => Role::Outside,
+        }
+    }
+
+    /// true if MPE is on with a zone to play, which is when the first part needs its member
+    /// engines.
+    pub fn has_zone(&self) -> bool {
+        self.enabled && (self.lower > 0 || self.upper > 0)
+    }
+
+    /// true if `msg` is on a channel in a zone, which gets past the receive channel filter.
+    pub fn covers(&self, msg: &MidiMessage) -> bool {
+        channel_of(msg).is_some_and(|channel| self.role(channel) != Role::Outside)
+    }
+
+    /// applies an MPE Configuration Message (RPN 6) from `master`: the zone is resized, MPE is on
+    /// as long as either zone has members, and member bends go back to their default range.
+    pub fn configure(&mut self, master: u8, members: u8) {
+        if master != LOWER_MASTER && master != UPPER_MASTER {
+            return;
+        }
+
+        self.set_zone(master, members);
+        self.enabled = self.lower > 0 || self.upper > 0;
+        self.member_bend = DEFAULT_MEMBER_BEND;
+    }
+
+    /// sets the member count of the zone mastered on `master`. the other zone shrinks to make
+    /// room. a channel that can't be a master is ignored.
+    pub fn set_zone(&mut self, master: u8, members: u8) {
+        let members = members.min(MAX_MEMBERS);
+
+        // with both zones in use, their masters and members have to fit in 16 channels.
+        let room = |other: u8| match (other, members) {
+            (0, _) | (_, 0) => other,
+            (other, members) => other.min((MAX_MEMBERS - 1).saturating_sub(members)),
+        };
+
+        match master {
+            LOWER_MASTER => {
+                self.lower = members;
+                self.upper = room(self.upper);
+            }
+            UPPER_MASTER => {
+                self.upper = members;
+                self.lower = room(self.lower);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zones(lower: u8, upper: u8) -> MpeConfig {
+        MpeConfig {
+            enabled: true,
+            lower,
+            upper,
+            ..MpeConfig::default()
+        }
+    }
+
+    /// both masters and every member channel fit in 16 channels.
+    fn fits(config: &MpeConfig) -> bool {
+        match (config.lower, config.upper) {
+            (0, upper) => upper <= MAX_MEMBERS,
+            (lower, 0) => lower <= MAX_MEMBERS,
+            (lower, upper) => lower + upper <= MAX_MEMBERS - 1,
+        }
+    }
+
+    #[test]
+    fn set_zone_makes_room() {
+        let mut config = zones(15, 0);
+        config.set_zone(UPPER_MASTER, 5);
+        assert_eq!((config.lower, config.upper), (9, 5));
+
+        config.set_zone(LOWER_MASTER, 12);
+        assert_eq!((config.lower, config.upper), (12, 2));
+
+        config.set_zone(LOWER_MASTER, 15);
+        assert_eq!((config.lower, config.upper), (15, 0));
+
+        // a zone that's already small enough is left alone.
+        let mut config = zones(3, 4);
+        config.set_zone(UPPER_MASTER, 10);
+        assert_eq!((config.lower, config.upper), (3, 10));
+
+        // emptying a zone gives the other all the room it had.
+        config.set_zone(UPPER_MASTER, 0);
+        assert_eq!((config.lower, config.upper), (3, 0));
+    }
+
+    #[test]
+    fn set_zone_always_fits() {
+        for lower in 0..=MAX_MEMBERS {
+            for upper in 0..=MAX_MEMBERS {
+                for members in 0..=20 {
+                    for master in [LOWER_MASTER, UPPER_MASTER] {
+                        let mut config = zones(lower, upper);
+                        config.set_zone(master, members);
+
+                        assert!(fits(&config), "{lower}/{upper} then {members} on {master}");
+
+                        let set = if master == LOWER_MASTER {
+                            config.lower
+                        } else {
+                            config.upper
+                        };
+                        assert_eq!(set, members.min(MAX_MEMBERS));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_zone_ignores_other_channels() {
+        let mut config = zones(7, 7);
+        config.set_zone(8, 3);
+        assert_eq!((config.lower, config.upper), (7, 7));
+    }
+
+    #[test]
+    fn roles() {
+        let config = zones(7, 7);
+        let roles: Vec<Role> = (1..=16).map(|channel| config.role(channel)).collect();
+
+        assert_eq!(roles[0], Role::Master);
+        assert!(roles[1..8].iter().all(|role| *role == Role::Member));
+        assert!(roles[8..15].iter().all(|role| *role == Role::Member));
+        assert_eq!(roles[15], Role::Master);
+
+        let config = zones(4, 0);
+        assert_eq!(config.role(1), Role::Master);
+        assert_eq!(config.role(5), Role::Member);
+        assert_eq!(config.role(6), Role::Outside);
+        assert_eq!(config.role(16), Role::Outside);
+
+        let off = MpeConfig::default();
+        assert!((1..=16).all(|channel| off.role(channel) == Role::Outside));
+    }
+
+    #[test]
+    fn has_zone() {
+        assert!(zones(15, 0).has_zone());
+        assert!(zones(0, 3).has_zone());
+        assert!(!zones(0, 0).has_zone());
+        assert!(!MpeConfig::default().has_zone());
+    }
+
+    #[test]
+    fn configure() {
+        let mut config = MpeConfig {
+            member_bend: 2.0,
+            ..MpeConfig::default()
+        };
+
+        config.configure(UPPER_MASTER, 6);
+        assert!(config.enabled);
+        assert_eq!((config.lower, config.upper), (8, 6));
+        assert_eq!(config.member_bend, DEFAULT_MEMBER_BEND);
+
+        config.configure(LOWER_MASTER, 0);
+        config.configure(UPPER_MASTER, 0);
+        assert!(!config.enabled);
+
+        // only the masters can configure a zone.
+        config.configure(2, 5);
+        assert!(!config.enabled);
+        assert_eq!((config.lower, config.upper), (0, 0));
+    }
+}
@@ -0,0 +1,175 @@
+//! MIDI going out to other devices: a soft thru of what the inputs receive, notes played on the
+//! screen, and parameter changes as CCs. everything is queued for one thread that writes to
+//! every output, so the UI and the router never wait on a device.
+
+use super::channel::channel_number;
+use super::learn::Target;
+use crate::config::CONFIG;
+use crate::synth::stereo::{PAN_CC, SPREAD_CC, WIDTH_CC};
+use crossbeam::channel::{bounded, Receiver, Sender};
+use lazy_static::lazy_static;
+use midi_control::{ControlEvent, KeyEvent, MidiMessage};
+use serde::{Deserialize, Serialize};
+use std::thread::JoinHandle;
+
+/// messages that can wait for the output thread. past this they're dropped, which is also what
+/// happens when there are no outputs to drain them.
+const OUT_QUEUE_SIZE: usize = 256;
+
+lazy_static! {
+    static ref MIDI_OUT: (Sender<Vec<u8>>, Receiver<Vec<u8>>) = bounded(OUT_QUEUE_SIZE);
+}
+
+/// somewhere MIDI can be written, like a USB device on android or an ALSA port.
+pub trait MidiOutput: Send + 'static {
+    fn name(&self) -> String;
+
+    /// writes one or more complete messages. failures are logged, not returned, one bad output
+    /// shouldn't stop the others.
+    fn write(&mut self, bytes: &[u8]);
+}
+
+/// what gets sent out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// forward everything the inputs receive, before the channel filters.
+    pub thru: bool,
+    /// notes played on the on-screen keyboard.
+    pub local_notes: bool,
+    /// parameter changes made in the UI, as CCs.
+    pub params: bool,
+    /// the channel, 1 through 16, that notes and CCs from the UI go out on.
+    pub channel: u8,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            thru: false,
+            local_notes: true,
+            params: true,
+            channel: 1,
+        }
+    }
+}
+
+/// starts the thread that writes queued messages to `outputs`. does nothing without outputs.
+pub fn spawn_outputs(mut outputs: Vec<Box<dyn MidiOutput>>) -> Option<JoinHandle<()>> {
+    if outputs.is_empty() {
+        return None;
+    }
+
+    for output in outputs.iter() {
+        log::info!("sending MIDI to {}", output.name());
+    }
+
+    let handle = std::thread::Builder::new()
+        .name("midi-out".into())
+        .spawn(move || {
+            while let Ok(bytes) = MIDI_OUT.1.recv() {
+                for output in outputs.iter_mut() {
+                    output.write(&bytes);
+                }
+            }
+        })
+        .expect("could not spawn MIDI output thread");
+
+    Some(handle)
+}
+
+/// queues raw bytes, which must be complete messages.
+pub fn send(bytes: Vec<u8>) {
+    if MIDI_OUT.0.try_send(bytes).is_err() {
+        log::debug!("MIDI output queue is full, dropping a message");
+    }
+}
+
+/// the bytes of a channel voice message. `None` for everything else, which isn't sent.
+pub fn encode(msg: &MidiMessage) -> Option<Vec<u8>> {
+    let status = |kind: u8, ch| kind | (channel_number(ch) - 1);
+
+    let bytes = match *msg {
+        MidiMessage::NoteOff(ch, KeyEvent { key, value }) => vec![status(0x80, ch), key, value],
+        MidiMessage::NoteOn(ch, KeyEvent { key, value }) => vec![status(0x90, ch), key, value],
+        MidiMessage::PolyKeyPressure(ch, KeyEvent { key, value }) => {
+            vec![status(0xa0, ch), key, value]
+        }
+        MidiMessage::ControlChange(ch, ControlEvent { control, value }) => {
+            vec![status(0xb0, ch), control, value]
+        }
+        MidiMessage::ProgramChange(ch, program) => vec![status(0xc0, ch), program],
+        MidiMessage::ChannelPressure(ch, value) => vec![status(0xd0, ch), value],
+        MidiMessage::PitchBend(ch, lsb, msb) => vec![status(0xe0, ch), lsb, msb],
+        _ => return None,
+    };
+
+    Some(
+        bytes
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| if i == 0 { b } else { b & 0x7f })
+            .collect(),
+    )
+}
+
+fn config() -> OutputConfig {
+    CONFIG
+        .read()
+        .map(|config| config.midi_out)
+        .unwrap_or_default()
+}
+
+/// forwards a message from an input if thru is on.
+pub fn thru(msg: &MidiMessage) {
+    if !config().thru {
+        return;
+    }
+
+    if let Some(bytes) = encode(msg) {
+        send(bytes);
+    }
+}
+
+/// a note from the on-screen keyboard. `velocity` 0 is a note off.
+pub fn local_note(key: u8, velocity: u8) {
+    let config = config();
+
+    if !config.local_notes {
+        return;
+    }
+
+    let channel = config.channel.clamp(1, 16) - 1;
+    let bytes = if velocity == 0 {
+        vec![0x80 | channel, key & 0x7f, 0]
+    } else {
+        vec![0x90 | channel, key & 0x7f, velocity & 0x7f]
+    };
+
+    send(bytes);
+}
+
+/// a parameter changed in the UI, `value` 0 to 1. goes out on the CC it is bound to in the
+/// mapping table, scaled back through the binding, or on its fixed CC if it isn't bound.
+pub fn local_param(target: Target, value: f32) {
+    let Ok(config) = CONFIG.read() else {
+        return;
+    };
+
+    if !config.midi_out.params {
+        return;
+    }
+
+    let fixed = |control| (control, (value.clamp(0.0, 1.0) * 127.0).round() as u8);
+    let binding = config.mappings.bindings.iter().find(|b| b.target == target);
+    let (control, value) = match (binding, target) {
+        (Some(binding), _) => (binding.control, binding.unscale(value)),
+        (None, Target::Pan) => fixed(PAN_CC),
+        (None, Target::Width) => fixed(WIDTH_CC),
+        (None, Target::Spread) => fixed(SPREAD_CC),
+        (None, _) => return,
+    };
+    let channel = config.midi_out.channel.clamp(1, 16) - 1;
+
+    send(vec![0xb0 | channel, control & 0x7f, value & 0x7f]);
+}
@@ -0,0 +1,325 @@
+//! turns a raw MIDI byte stream into messages. bytes can arrive in chunks of any size: a chunk
+//! may hold several messages, use running status, have realtime bytes dropped in the middle of
+//! a message, or carry only part of a SysEx. keep one parser per source, since the state carries
+//! over from one chunk to the next.
+
+use super::SystemMessage;
+use midi_control::MidiMessage;
+
+/// SysEx longer than this is skipped rather than buffered.
+pub const MAX_SYSEX: usize = 64 * 1024;
+
+const SYSEX_START: u8 = 0xf0;
+const SYSEX_END: u8 = 0xf7;
+const SONG_POSITION: u8 = 0xf2;
+
+/// what a byte finished. messages come with the bytes they arrived as.
+enum Parsed {
+    Message(MidiMessage, Vec<u8>),
+    System(SystemMessage),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MidiParser {
+    /// the status of the message being read. for channel messages it stays after the message is
+    /// done, which is running status.
+    status: Option<u8>,
+    /// data bytes read so far for `status`.
+    data: [u8; 2],
+    len: usize,
+    /// the bytes of the message being read as they came, without the status under running
+    /// status.
+    raw: Vec<u8>,
+    /// the SysEx being read, from its `0xf0` on. empty when not in one.
+    sysex: Vec<u8>,
+    /// the SysEx being read got too long and is being skipped until it ends.
+    sysex_overflow: bool,
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// feeds `bytes` in, calling `emit` for every message they complete. clock and transport
+    /// messages are dropped, see `push_with_system`.
+    pub fn push(&mut self, bytes: &[u8], emit: impl FnMut(MidiMessage)) {
+        self.push_with_system(bytes, emit, |_| {});
+    }
+
+    /// like `push`, but clock and transport messages go to `system`.
+    pub fn push_with_system(
+        &mut self,
+        bytes: &[u8],
+        mut emit: impl FnMut(MidiMessage),
+        system: impl FnMut(SystemMessage),
+    ) {
+        self.push_raw(bytes, |msg, _| emit(msg), system);
+    }
+
+    /// like `push_with_system`, but every message comes with the bytes it arrived as, for the
+    /// monitor. realtime bytes in the middle of a message aren't among them.
+    pub fn push_raw(
+        &mut self,
+        bytes: &[u8],
+        mut emit: impl FnMut(MidiMessage, &[u8]),
+        mut system: impl FnMut(SystemMessage),
+    ) {
+        for &byte in bytes {
+            match self.byte(byte) {
+                Some(Parsed::Message(msg, raw)) => emit(msg, &raw),
+                Some(Parsed::System(msg)) => system(msg),
+                None => {}
+            }
+        }
+    }
+
+    /// forgets any half read message and the running status, for when the stream breaks.
+    pub fn reset(&mut self) {
+        self.status = None;
+        self.len = 0;
+        self.raw.clear();
+        self.sysex.clear();
+        self.sysex_overflow = false;
+    }
+
+    fn in_sysex(&self) -> bool {
+        !self.sysex.is_empty() || self.sysex_overflow
+    }
+
+    /// drops an unfinished SysEx, a status byte other than its end cuts it off.
+    fn abandon_sysex(&mut self) {
+        if self.in_sysex() {
+            log::warn!("dropping a SysEx that was cut off");
+        }
+
+        self.sysex.clear();
+        self.sysex_overflow = false;
+    }
+
+    fn byte(&mut self, byte: u8) -> Option<Parsed> {
+        match byte {
+            // realtime bytes can come anywhere, even inside other messages, and don't disturb
+            // them.
+            0xf8..=0xff => SystemMessage::realtime(byte).map(Parsed::System),
+            SYSEX_START => {
+                self.abandon_sysex();
+                self.status = None;
+                self.len = 0;
+                self.raw.clear();
+                self.sysex.push(SYSEX_START);
+
+                None
+            }
+            SYSEX_END => {
+                if !self.in_sysex() {
+                    return None;
+                }
+
+                if self.sysex_overflow {
+                    log::warn!("skipped a SysEx longer than {MAX_SYSEX} bytes");
+                    self.sysex.clear();
+                    self.sysex_overflow = false;
+
+                    return None;
+                }
+
+                self.sysex.push(SYSEX_END);
+                let msg = MidiMessage::from(self.sysex.as_slice());
+
+                Some(Parsed::Message(msg, std::mem::take(&mut self.sysex)))
+            }
+            0x80..=0xf6 => {
+                self.abandon_sysex();
+                self.status = Some(byte);
+                self.len = 0;
+                self.raw.clear();
+                self.raw.push(byte);
+
+                // tune request and the undefined ones have no data, and nothing uses them.
+                if data_len(byte) == 0 {
+                    self.status = None;
+                }
+
+                None
+            }
+            data => self.data_byte(data),
+        }
+    }
+
+    fn data_byte(&mut self, byte: u8) -> Option<Parsed> {
+        // the rest of a SysEx that got too long is skipped up to its end.
+        if self.sysex_overflow {
+            return None;
+        }
+
+        if self.in_sysex() {
+            if self.sysex.len() < MAX_SYSEX {
+                self.sysex.push(byte);
+            } else {
+                self.sysex.clear();
+                self.sysex_overflow = true;
+            }
+
+            return None;
+        }
+
+        // data with no status to go with it is dropped.
+        let status = self.status?;
+        let needed = data_len(status);
+
+        self.data[self.len] = byte;
+        self.len += 1;
+        self.raw.push(byte);
+
+        if self.len < needed {
+            return None;
+        }
+
+        self.len = 0;
+        let raw = std::mem::take(&mut self.raw);
+
+        // system common messages don't take part in running status. song position is the only
+        // one used.
+        if status >= 0xf0 {
+            self.status = None;
+
+            return (status == SONG_POSITION).then(|| {
+                let beats = (self.data[1] as u16) << 7 | self.data[0] as u16;
+                Parsed::System(SystemMessage::SongPosition(beats))
+            });
+        }
+
+        let bytes = [status, self.data[0], self.data[1]];
+
+        Some(Parsed::Message(
+            MidiMessage::from(&bytes[..1 + needed]),
+            raw,
+        ))
+    }
+}
+
+/// data bytes that follow `status`.
+fn data_len(status: u8) -> usize {
+    match status {
+        0xc0..=0xdf => 1,
+        0x80..=0xef => 2,
+        // MTC quarter frame and song select.
+        0xf1 | 0xf3 => 1,
+        // song position.
+        0xf2 => 2,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// feeds `chunks` in one at a time, collecting what comes out. messages are compared by
+    /// how they print, like the fuzz target does.
+    fn parse(parser: &mut MidiParser, chunks: &[&[u8]]) -> (Vec<String>, Vec<SystemMessage>) {
+        let mut messages = Vec::new();
+        let mut system = Vec::new();
+
+        for chunk in chunks {
+            parser.push_with_system(
+                chunk,
+                |msg| messages.push(format!("{msg:?}")),
+                |msg| system.push(msg),
+            );
+        }
+
+        (messages, system)
+    }
+
+    fn msg(bytes: &[u8]) -> String {
+        format!("{:?}", MidiMessage::from(bytes))
+    }
+
+    #[test]
+    fn running_status() {
+        let mut parser = MidiParser::new();
+        let (messages, _) = parse(&mut parser, &[&[0x90, 60, 100, 62, 90, 60, 0]]);
+
+        assert_eq!(
+            messages,
+            [
+                msg(&[0x90, 60, 100]),
+                msg(&[0x90, 62, 90]),
+                msg(&[0x90, 60, 0])
+            ]
+        );
+    }
+
+    #[test]
+    fn realtime_inside_a_message() {
+        let mut parser = MidiParser::new();
+        let (messages, system) = parse(&mut parser, &[&[0x90, 60, 0xf8, 100, 0xfa]]);
+
+        assert_eq!(messages, [msg(&[0x90, 60, 100])]);
+        assert_eq!(system, [SystemMessage::Clock, SystemMessage::Start]);
+    }
+
+    #[test]
+    fn sysex_split_across_chunks() {
+        let mut parser = MidiParser::new();
+        let (messages, _) = parse(&mut parser, &[&[0xf0, 0x7e, 0x7f]]);
+        assert!(messages.is_empty());
+
+        let (messages, _) = parse(&mut parser, &[&[0x06], &[0x01, 0xf7]]);
+        assert_eq!(messages, [msg(&[0xf0, 0x7e, 0x7f, 0x06, 0x01, 0xf7])]);
+    }
+
+    #[test]
+    fn several_messages_in_one_chunk() {
+        let mut parser = MidiParser::new();
+        let (messages, system) = parse(
+            &mut parser,
+            &[&[0x90, 60, 100, 0xb1, 7, 64, 0xc2, 5, 0xe3, 0, 64, 0xf2, 4, 1]],
+        );
+
+        assert_eq!(
+            messages,
+            [
+                msg(&[0x90, 60, 100]),
+                msg(&[0xb1, 7, 64]),
+                msg(&[0xc2, 5]),
+                msg(&[0xe3, 0, 64])
+            ]
+        );
+        assert_eq!(system, [SystemMessage::SongPosition(1 << 7 | 4)]);
+    }
+
+    #[test]
+    fn raw_bytes_as_received() {
+        let mut parser = MidiParser::new();
+        let mut raw = Vec::new();
+        parser.push_raw(
+            &[0x90, 60, 0xf8, 100, 62, 90, 0xf0, 0x7e, 0xf7],
+            |_, bytes| raw.push(bytes.to_vec()),
+            |_| {},
+        );
+
+        assert_eq!(
+            raw,
+            [vec![0x90, 60, 100], vec![62, 90], vec![0xf0, 0x7e, 0xf7]]
+        );
+    }
+
+    #[test]
+    fn oversize_sysex_is_skipped() {
+        let mut parser = MidiParser::new();
+        let mut sysex = vec![SYSEX_START];
+        sysex.extend(std::iter::repeat(0x01).take(MAX_SYSEX + 10));
+        sysex.push(SYSEX_END);
+
+        let (messages, _) = parse(&mut parser, &[sysex.as_slice()]);
+        assert!(messages.is_empty());
+        assert!(!parser.in_sysex());
+
+        // nothing of the SysEx is left over to swallow what comes next.
+        let (messages, _) = parse(&mut parser, &[&[0x90, 60, 100]]);
+        assert_eq!(messages, [msg(&[0x90, 60, 100])]);
+    }
+}
@@ -0,0 +1,234 @@
+//! registered parameter numbers. a controller picks a parameter with CCs 101 and 100 and then
+//! sets it with data entry (CCs 6 and 38) or increment/decrement (CCs 96 and 97). the synth
+//! knows RPN 0, the pitch bend range, and RPN 6, the MPE Configuration Message.
+
+use crate::synth::BendRange;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+pub const DATA_ENTRY_MSB_CC: u8 = 6;
+pub const DATA_ENTRY_LSB_CC: u8 = 38;
+pub const DATA_INCREMENT_CC: u8 = 96;
+pub const DATA_DECREMENT_CC: u8 = 97;
+pub const NRPN_LSB_CC: u8 = 98;
+pub const NRPN_MSB_CC: u8 = 99;
+pub const RPN_LSB_CC: u8 = 100;
+pub const RPN_MSB_CC: u8 = 101;
+
+/// pitch bend range, data entry MSB is semitones and LSB is cents. increment and decrement step
+/// the LSB, a cent at a time, as RP-018 says.
+pub const BEND_RANGE_RPN: u16 = 0;
+/// MPE configuration, sent on a zone's master channel. data entry MSB is the number of member
+/// channels.
+pub const MPE_CONFIGURATION_RPN: u16 = 6;
+
+/// a parameter change that came in through data entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rpn {
+    BendRange(BendRange),
+    /// the member channel count of the zone mastered on the channel it came on.
+    MpeConfiguration(u8),
+}
+
+lazy_static! {
+    /// the parameter selected on each channel.
+    static ref SELECTED: Mutex<[Selection; 16]> = Mutex::new([Selection::default(); 16]);
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Selection {
+    msb: Option<u8>,
+    lsb: Option<u8>,
+    /// the last select was for a non-registered parameter, none of which we know.
+    nrpn: bool,
+}
+
+impl Selection {
+    fn rpn(&self) -> Option<u16> {
+        match (self.nrpn, self.msb, self.lsb) {
+            (false, Some(msb), Some(lsb)) => Some((msb as u16) << 7 | lsb as u16),
+            _ => None,
+        }
+    }
+}
+
+/// feeds a CC on `channel` (1 through 16) to the RPN state. `range` is the channel's current bend
+/// range. returns the change when it was data for a known parameter and so shouldn't go
+/// anywhere else, `None` otherwise.
+pub fn control(channel: u8, control: u8, value: u8, range: BendRange) -> Option<Rpn> {
+    let Ok(mut selected) = SELECTED.lock() else {
+        return None;
+    };
+    let selection = selected.get_mut(channel.wrapping_sub(1) as usize)?;
+
+    match control {
+        RPN_MSB_CC | RPN_LSB_CC if selection.nrpn => {
+            *selection = Selection::default();
+        }
+        NRPN_MSB_CC | NRPN_LSB_CC => {
+            *selection = Selection {
+                nrpn: true,
+                ..Selection::default()
+            };
+        }
+        _ => {}
+    }
+
+    match control {
+        RPN_MSB_CC => selection.msb = Some(value),
+        RPN_LSB_CC => selection.lsb = Some(value),
+        _ => {}
+    }
+
+    match selection.rpn() {
+        Some(BEND_RANGE_RPN) => bend_range(control, value, range).map(Rpn::BendRange),
+        Some(MPE_CONFIGURATION_RPN) if control == DATA_ENTRY_MSB_CC => {
+            Some(Rpn::MpeConfiguration(value))
+        }
+        _ => None,
+    }
+}
+
+fn bend_range(control: u8, value: u8, range: BendRange) -> Option<BendRange> {
+    let semitones = range.up.floor();
+
+    match control {
+        // a new MSB starts the cents over.
+        DATA_ENTRY_MSB_CC => Some(BendRange::symmetric(value as f32)),
+        DATA_ENTRY_LSB_CC => Some(BendRange::symmetric(
+            semitones + value.min(99) as f32 / 100.0,
+        )),
+        DATA_INCREMENT_CC => Some(step_cents(range, 1.0)),
+        DATA_DECREMENT_CC => Some(step_cents(range, -1.0)),
+        _ => None,
+    }
+}
+
+/// moves `range` by `cents`, carrying into the semitones.
+fn step_cents(range: BendRange, cents: f32) -> BendRange {
+    BendRange::symmetric(((range.up * 100.0).round() + cents) / 100.0)
+}
+
+/// deselects the parameter on `channel`, as Reset All Controllers asks.
+pub fn reset(channel: u8) {
+    if let Ok(mut selected) = SELECTED.lock() {
+        if let Some(selection) = selected.get_mut(channel.wrapping_sub(1) as usize) {
+            *selection = Selection::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the selections are global, so each test keeps to channels of its own.
+
+    fn select(channel: u8, msb: u8, lsb: u8) {
+        let range = BendRange::default();
+        assert_eq!(control(channel, RPN_MSB_CC, msb, range), None);
+        assert_eq!(control(channel, RPN_LSB_CC, lsb, range), None);
+    }
+
+    fn bend(channel: u8, cc: u8, value: u8, range: BendRange) -> Option<BendRange> {
+        match control(channel, cc, value, range) {
+            Some(Rpn::BendRange(range)) => Some(range),
+            _ => None,
+        }
+    }
+
+    fn close(range: Option<BendRange>, semitones: f32) -> bool {
+        range.is_some_and(|range| (range.up - semitones).abs() < 1.0e-4 && range.up == range.down)
+    }
+
+    #[test]
+    fn bend_range_data_entry() {
+        select(1, 0, 0);
+        let range = bend(1, DATA_ENTRY_MSB_CC, 12, BendRange::default());
+        assert_eq!(range, Some(BendRange::symmetric(12.0)));
+
+        let range = bend(1, DATA_ENTRY_LSB_CC, 50, range.unwrap());
+        assert!(close(range, 12.5));
+
+        // a new MSB starts the cents over.
+        let range = bend(1, DATA_ENTRY_MSB_CC, 7, range.unwrap());
+        assert_eq!(range, Some(BendRange::symmetric(7.0)));
+
+        // cents past 99 aren't cents.
+        assert!(close(bend(1, DATA_ENTRY_LSB_CC, 127, range.unwrap()), 7.99));
+    }
+
+    #[test]
+    fn select_in_either_order() {
+        let range = BendRange::default();
+        assert_eq!(control(2, RPN_LSB_CC, 0, range), None);
+        assert_eq!(control(2, DATA_ENTRY_MSB_CC, 5, range), None);
+        assert_eq!(control(2, RPN_MSB_CC, 0, range), None);
+        assert_eq!(
+            bend(2, DATA_ENTRY_MSB_CC, 5, range),
+            Some(BendRange::symmetric(5.0))
+        );
+
+        // channels keep their own selection.
+        assert_eq!(control(3, DATA_ENTRY_MSB_CC, 5, range), None);
+    }
+
+    #[test]
+    fn increment_steps_cents() {
+        select(4, 0, 0);
+        let range = BendRange::symmetric(2.0);
+
+        assert!(close(bend(4, DATA_INCREMENT_CC, 0, range), 2.01));
+        assert!(close(bend(4, DATA_DECREMENT_CC, 0, range), 1.99));
+        assert!(close(
+            bend(4, DATA_INCREMENT_CC, 0, BendRange::symmetric(2.99)),
+            3.0
+        ));
+        assert!(close(
+            bend(4, DATA_DECREMENT_CC, 0, BendRange::symmetric(0.0)),
+            0.0
+        ));
+    }
+
+    #[test]
+    fn null_rpn_deselects() {
+        select(5, 0, 0);
+        assert!(bend(5, DATA_ENTRY_MSB_CC, 3, BendRange::default()).is_some());
+
+        select(5, 127, 127);
+        assert_eq!(control(5, DATA_ENTRY_MSB_CC, 3, BendRange::default()), None);
+        assert_eq!(control(5, DATA_INCREMENT_CC, 0, BendRange::default()), None);
+    }
+
+    #[test]
+    fn nrpn_deselects() {
+        select(6, 0, 0);
+        let range = BendRange::default();
+        assert_eq!(control(6, NRPN_MSB_CC, 0, range), None);
+        assert_eq!(control(6, NRPN_LSB_CC, 0, range), None);
+        assert_eq!(control(6, DATA_ENTRY_MSB_CC, 3, range), None);
+
+        select(6, 0, 0);
+        assert!(bend(6, DATA_ENTRY_MSB_CC, 3, range).is_some());
+    }
+
+    #[test]
+    fn reset_deselects() {
+        select(7, 0, 0);
+        reset(7);
+        assert_eq!(control(7, DATA_ENTRY_MSB_CC, 3, BendRange::default()), None);
+    }
+
+    #[test]
+    fn mpe_configuration() {
+        select(16, 0, 6);
+        assert_eq!(
+            control(16, DATA_ENTRY_MSB_CC, 4, BendRange::default()),
+            Some(Rpn::MpeConfiguration(4))
+        );
+        assert_eq!(
+            control(16, DATA_ENTRY_LSB_CC, 0, BendRange::default()),
+            None
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! places MIDI comes from. every source runs on its own thread and feeds the shared `MIDI_SEND`
+//! channel, so any number of them can play the synth at once.
+
+use super::{MidiEvent, SystemMessage};
+use crossbeam::channel::{Receiver, RecvTimeoutError, SendError, Sender};
+use lazy_static::lazy_static;
+use midi_control::MidiMessage;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::render::load_timeline;
+
+/// how often blocking sources wake up to check if they were asked to stop.
+pub const STOP_POLL: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    /// the names of every source started so far, for the per-device settings.
+    pub static ref KNOWN_SOURCES: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+}
+
+pub trait MidiSource: Send + 'static {
+    fn name(&self) -> String;
+
+    /// reads MIDI into `sink` until the source runs dry, `sink` is disconnected, or `stop` is set.
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>);
+}
+
+/// where a source puts its messages. tags each one with the source's name so the router can tell
+/// devices apart.
+#[derive(Debug, Clone)]
+pub struct MidiSink {
+    source: Arc<str>,
+    sink: Sender<MidiEvent>,
+}
+
+impl MidiSink {
+    pub fn new(source: &str, sink: Sender<MidiEvent>) -> Self {
+        Self {
+            source: source.into(),
+            sink,
+        }
+    }
+
+    /// sends `msg` stamped with the current time. fails once nothing is listening anymore.
+    pub fn send(&self, msg: MidiMessage) -> Result<(), SendError<MidiEvent>> {
+        self.send_at(msg, Instant::now())
+    }
+
+    /// sends `msg` stamped with when it really arrived, for sources that know.
+    pub fn send_at(&self, msg: MidiMessage, time: Instant) -> Result<(), SendError<MidiEvent>> {
+        self.send_raw(msg, Vec::new(), time)
+    }
+
+    /// sends `msg` along with the bytes it was parsed from, for sources that read byte streams.
+    pub fn send_raw(
+        &self,
+        msg: MidiMessage,
+        raw: Vec<u8>,
+        time: Instant,
+    ) -> Result<(), SendError<MidiEvent>> {
+        self.sink.send(MidiEvent {
+            source: self.source.clone(),
+            msg,
+            raw,
+            time,
+        })
+    }
+
+    /// hands a clock or transport message to the transport, see `super::system`.
+    pub fn system(&self, msg: SystemMessage, time: Instant) {
+        super::system(&self.source, msg, time);
+    }
+
+    /// sends `msg` as coming from `source` rather than this sink's source, for bridges that
+    /// carry several devices.
+    pub fn send_as(
+        &self,
+        source: Arc<str>,
+        msg: MidiMessage,
+        raw: Vec<u8>,
+        time: Instant,
+    ) -> Result<(), SendError<MidiEvent>> {
+        self.sink.send(MidiEvent {
+            source,
+            msg,
+            raw,
+            time,
+        })
+    }
+}
+
+/// a running source, stopped when dropped.
+#[derive(Debug)]
+pub struct SourceHandle {
+    pub name: String,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SourceHandle {
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+}
+
+impl Drop for SourceHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// starts `source` on its own thread, feeding `sink`.
+pub fn spawn_source(source: Box<dyn MidiSource>, sink: Sender<MidiEvent>) -> SourceHandle {
+    let name = source.name();
+    let stop = Arc::new(AtomicBool::new(false));
+    let sink = MidiSink::new(&name, sink);
+
+    log::info!("starting MIDI source: {name}");
+
+    if let Ok(mut known) = KNOWN_SOURCES.write() {
+        known.insert(name.clone());
+    }
+
+    let handle = std::thread::Builder::new()
+        .name(format!("midi-src {name}"))
+        .spawn({
+            let stop = stop.clone();
+            let name = name.clone();
+
+            move || {
+                source.run(sink, stop);
+                log::info!("MIDI source {name} finished");
+            }
+        })
+        .expect("could not spawn MIDI source thread");
+
+    SourceHandle {
+        name,
+        stop,
+        handle: Some(handle),
+    }
+}
+
+/// a message pushed to a `ChannelSource`.
+#[derive(Debug, Clone)]
+pub struct Pushed {
+    /// the device it came from, when the bridge carries more than one. `None` tags it with the
+    /// bridge's own name.
+    pub device: Option<Arc<str>>,
+    /// when it arrived.
+    pub time: Instant,
+    pub msg: MidiMessage,
+    /// the bytes it arrived as, empty if the bridge doesn't have them.
+    pub raw: Vec<u8>,
+}
+
+/// forwards messages from a channel, for bridges that get MIDI pushed to them from elsewhere
+/// (like a JNI callback).
+pub struct ChannelSource {
+    name: String,
+    recv: Receiver<Pushed>,
+}
+
+impl ChannelSource {
+    pub fn new(name: impl Into<String>, recv: Receiver<Pushed>) -> Self {
+        Self {
+            name: name.into(),
+            recv,
+        }
+    }
+}
+
+impl MidiSource for ChannelSource {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>) {
+        while !stop.load(Ordering::Relaxed) {
+            match self.recv.recv_timeout(STOP_POLL) {
+                Ok(Pushed {
+                    device,
+                    time,
+                    msg,
+                    raw,
+                }) => {
+                    let sent = match device {
+                        Some(device) => sink.send_as(device, msg, raw, time),
+                        None => sink.send_raw(msg, raw, time),
+                    };
+
+                    if sent.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// sends a fixed list of messages as fast as possible, then finishes. for tests.
+pub struct MemorySource {
+    pub messages: Vec<MidiMessage>,
+}
+
+impl MidiSource for MemorySource {
+    fn name(&self) -> String {
+        "memory".into()
+    }
+
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>) {
+        for msg in self.messages {
+            if stop.load(Ordering::Relaxed) || sink.send(msg).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// plays a Standard MIDI File in real time, optionally looping.
+pub struct ReplaySource {
+    pub path: PathBuf,
+    pub looping: bool,
+}
+
+impl MidiSource for ReplaySource {
+    fn name(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>) {
+        let timeline = match load_timeline(&self.path) {
+            Ok(timeline) => timeline,
+            Err(e) => {
+                log::error!("{e}");
+                return;
+            }
+        };
+
+        loop {
+            let start = Instant::now();
+
+            for (secs, msg) in timeline.iter() {
+                let at = start + Duration::from_secs_f64(*secs);
+
+                // sleep in short steps so a stop request is not stuck behind a long rest.
+                while let Some(wait) = at.checked_duration_since(Instant::now()) {
+                    if stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+
+                    sleep(wait.min(STOP_POLL));
+                }
+
+                if sink.send(msg.clone()).is_err() {
+                    return;
+                }
+            }
+
+            if !self.looping || stop.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! keyboard zones: key and velocity ranges that each play a part of their own, transposed. one
+//! keyboard can play bass on the left and pads on the right, or stack two parts, or switch
+//! parts with how hard it is played. notes in a zone go to its part instead of the parts the
+//! channel would usually reach, and notes in none of a channel's zones aren't played, so a gap
+//! between zones stays silent. everything else on the channel (bend, pedals, CCs) reaches the
+//! zones' parts as well.
+
+use super::channel::ReceiveChannel;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// the furthest a zone can transpose, in semitones.
+pub const MAX_TRANSPOSE: i8 = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Zone {
+    /// the channel the zone listens on.
+    pub channel: ReceiveChannel,
+    /// the lowest and highest keys it takes, inclusive.
+    pub low_key: u8,
+    pub high_key: u8,
+    /// the softest and hardest notes it takes, inclusive.
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+    /// the part it plays, 0 through 15.
+    pub part: usize,
+    /// semitones added to every note.
+    pub transpose: i8,
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self {
+            channel: ReceiveChannel::Omni,
+            low_key: 0,
+            high_key: 127,
+            low_velocity: 1,
+            high_velocity: 127,
+            part: 0,
+            transpose: 0,
+        }
+    }
+}
+
+impl Zone {
+    /// true if the zone listens on `channel` (1 through 16) and `key` is in its range.
+    pub fn has_key(&self, channel: u8, key: u8) -> bool {
+        self.channel.accepts(channel) && (self.low_key..=self.high_key).contains(&key)
+    }
+
+    /// true if a note on of `key` at `velocity` sounds in this zone.
+    pub fn plays(&self, channel: u8, key: u8, velocity: u8) -> bool {
+        self.has_key(channel, key) && (self.low_velocity..=self.high_velocity).contains(&velocity)
+    }
+
+    /// `key` transposed, `None` if that falls off the keyboard.
+    pub fn transposed(&self, key: u8) -> Option<u8> {
+        let key = key as i16 + self.transpose.clamp(-MAX_TRANSPOSE, MAX_TRANSPOSE) as i16;
+        (0..128).contains(&key).then_some(key as u8)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZoneConfig {
+    pub enabled: bool,
+    pub list: Vec<Zone>,
+}
+
+impl ZoneConfig {
+    /// true if zones take the notes on `channel`.
+    pub fn covers(&self, channel: u8) -> bool {
+        self.enabled && self.list.iter().any(|zone| zone.channel.accepts(channel))
+    }
+
+    /// a bit per part a zone on `channel` plays, for the messages that aren't notes.
+    pub fn parts_on(&self, channel: u8) -> u16 {
+        if !self.enabled {
+            return 0;
+        }
+
+        self.list
+            .iter()
+            .filter(|zone| zone.channel.accepts(channel) && zone.part < 16)
+            .fold(0, |parts, zone| parts | 1 << zone.part)
+    }
+
+    /// true if some zone plays part `index`, which then has to be loaded.
+    pub fn targets(&self, index: usize) -> bool {
+        self.enabled && self.list.iter().any(|zone| zone.part == index)
+    }
+}
+
+/// where a held note went: its channel and key as played, and the part and key it sounds as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sounding {
+    pub channel: u8,
+    pub key: u8,
+    pub part: usize,
+    pub played: u8,
+}
+
+lazy_static! {
+    /// notes zones are sounding, so each note off reaches the part its note on did even if the
+    /// zones were changed in between.
+    static ref SOUNDING: Mutex<Vec<Sounding>> = Mutex::new(Vec::new());
+}
+
+/// the parts and keys a note on of `key` at `velocity` on `channel` plays, remembered for its
+/// note off.
+pub fn note_on(config: &ZoneConfig, channel: u8, key: u8, velocity: u8) -> Vec<Sounding> {
+    let targets: Vec<Sounding> = config
+        .list
+        .iter()
+        .filter(|zone| zone.plays(channel, key, velocity))
+        .filter_map(|zone| {
+            zone.transposed(key).map(|played| Sounding {
+                channel,
+                key,
+                part: zone.part,
+                played,
+            })
+        })
+        .collect();
+
+    if let Ok(mut sounding) = SOUNDING.lock() {
+        sounding.retain(|note| (note.channel, note.key) != (channel, key));
+        sounding.extend(targets.iter().copied());
+    }
+
+    targets
+}
+
+/// the parts and keys the note on of `key` on `channel` went to, forgotten from here on.
+pub fn note_off(channel: u8, key: u8) -> Vec<Sounding> {
+    let Ok(mut sounding) = SOUNDING.lock() else {
+        return Vec::new();
+    };
+
+    let (off, on) = sounding
+        .drain(..)
+        .partition(|note| (note.channel, note.key) == (channel, key));
+    *sounding = on;
+
+    off
+}
+
+/// the notes sounding on `key` of `channel`, for poly pressure.
+pub fn sounding(channel: u8, key: u8) -> Vec<Sounding> {
+    SOUNDING
+        .lock()
+        .map(|sounding| {
+            sounding
+                .iter()
+                .filter(|note| (note.channel, note.key) == (channel, key))
+                .copied()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// forgets every held note, after All Notes Off and the like.
+pub fn clear() {
+    if let Ok(mut sounding) = SOUNDING.lock() {
+        sounding.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the held notes are global, so each test keeps to channels of its own.
+
+    fn zone(channel: u8, keys: (u8, u8), part: usize, transpose: i8) -> Zone {
+        Zone {
+            channel: ReceiveChannel::Channel(channel),
+            low_key: keys.0,
+            high_key: keys.1,
+            part,
+            transpose,
+            ..Zone::default()
+        }
+    }
+
+    fn zones(list: Vec<Zone>) -> ZoneConfig {
+        ZoneConfig {
+            enabled: true,
+            list,
+        }
+    }
+
+    fn parts(notes: &[Sounding]) -> Vec<(usize, u8)> {
+        notes.iter().map(|note| (note.part, note.played)).collect()
+    }
+
+    #[test]
+    fn split() {
+        let config = zones(vec![zone(1, (0, 59), 1, -12), zone(1, (60, 127), 2, 0)]);
+
+        assert_eq!(parts(&note_on(&config, 1, 40, 100)), [(1, 28)]);
+        assert_eq!(parts(&note_on(&config, 1, 60, 100)), [(2, 60)]);
+        assert_eq!(parts(&note_off(1, 40)), [(1, 28)]);
+        assert_eq!(parts(&note_off(1, 60)), [(2, 60)]);
+    }
+
+    #[test]
+    fn layer() {
+        let config = zones(vec![zone(2, (0, 72), 1, 0), zone(2, (48, 127), 3, 7)]);
+
+        assert_eq!(parts(&note_on(&config, 2, 36, 100)), [(1, 36)]);
+        assert_eq!(parts(&note_on(&config, 2, 60, 100)), [(1, 60), (3, 67)]);
+        assert_eq!(parts(&sounding(2, 60)), [(1, 60), (3, 67)]);
+        assert_eq!(parts(&note_off(2, 60)), [(1, 60), (3, 67)]);
+        assert!(sounding(2, 60).is_empty());
+        assert_eq!(parts(&note_off(2, 36)), [(1, 36)]);
+    }
+
+    #[test]
+    fn velocity() {
+        let soft = Zone {
+            high_velocity: 63,
+            ..zone(3, (0, 127), 1, 0)
+        };
+        let hard = Zone {
+            low_velocity: 64,
+            ..zone(3, (0, 127), 2, 0)
+        };
+        let config = zones(vec![soft, hard]);
+
+        assert_eq!(parts(&note_on(&config, 3, 60, 40)), [(1, 60)]);
+        assert_eq!(parts(&note_on(&config, 3, 62, 100)), [(2, 62)]);
+        note_off(3, 60);
+        note_off(3, 62);
+    }
+
+    #[test]
+    fn outside_every_zone() {
+        let config = zones(vec![zone(4, (0, 47), 1, 0), zone(4, (72, 127), 2, 0)]);
+
+        // the channel belongs to the zones, so a note in the gap isn't played anywhere.
+        assert!(config.covers(4));
+        assert!(note_on(&config, 4, 60, 100).is_empty());
+        assert!(sounding(4, 60).is_empty());
+        assert!(note_off(4, 60).is_empty());
+
+        // other channels are left alone.
+        assert!(!config.covers(5));
+        assert_eq!(config.parts_on(4), 0b110);
+        assert_eq!(config.parts_on(5), 0);
+    }
+
+    #[test]
+    fn transpose_clamps() {
+        let up = zone(6, (0, 127), 1, 12);
+        assert_eq!(up.transposed(115), Some(127));
+        assert_eq!(up.transposed(116), None);
+
+        let down = zone(6, (0, 127), 1, -12);
+        assert_eq!(down.transposed(12), Some(0));
+        assert_eq!(down.transposed(11), None);
+
+        // past the furthest a zone can go, it goes as far as it can.
+        assert_eq!(
+            zone(6, (0, 127), 1, 100).transposed(0),
+            Some(MAX_TRANSPOSE as u8)
+        );
+        assert_eq!(
+            zone(6, (0, 127), 1, -100).transposed(127),
+            Some(127 - MAX_TRANSPOSE as u8)
+        );
+
+        // a note transposed off the keyboard isn't played, the rest of a layer still is.
+        let config = zones(vec![up, zone(6, (0, 127), 2, 0)]);
+        assert_eq!(parts(&note_on(&config, 6, 120, 100)), [(2, 120)]);
+        assert_eq!(parts(&note_off(6, 120)), [(2, 120)]);
+    }
+
+    #[test]
+    fn note_off_after_edit() {
+        let mut config = zones(vec![zone(7, (0, 127), 1, 5)]);
+        assert_eq!(parts(&note_on(&config, 7, 60, 100)), [(1, 65)]);
+
+        // the zone is moved to another part and transpose while the note is held.
+        config.list[0].part = 2;
+        config.list[0].transpose = 0;
+
+        assert_eq!(parts(&note_off(7, 60)), [(1, 65)]);
+        assert!(note_off(7, 60).is_empty());
+
+        config.enabled = false;
+        assert!(!config.covers(7));
+    }
+
+    #[test]
+    fn retrigger_replaces() {
+        let mut config = zones(vec![zone(8, (0, 127), 1, 0)]);
+        note_on(&config, 8, 60, 100);
+
+        config.list[0].part = 2;
+        assert_eq!(parts(&note_on(&config, 8, 60, 100)), [(2, 60)]);
+        assert_eq!(parts(&note_off(8, 60)), [(2, 60)]);
+    }
+
+    #[test]
+    fn targets() {
+        let config = zones(vec![zone(9, (0, 127), 4, 0)]);
+        assert!(config.targets(4));
+        assert!(!config.targets(0));
+        assert!(!ZoneConfig::default().targets(4));
+    }
+}
@@ -0,0 +1,293 @@
+//! stored patches, recalled by program change and bank select. kept as TOML in the platform's
+//! data directory next to the config, but in a file of their own (`patches.toml`) so a set of
+//! sounds can be copied between tablets without their MIDI setup.
+
+use crate::config::ConfigError;
+use crate::synth::{BendRange, PartEngine, StereoParams};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+pub const PATCHES_FILE: &str = "patches.toml";
+
+lazy_static! {
+    /// the stored patches, loaded by `run` along with the config.
+    pub static ref PATCHES: RwLock<PatchStore> = RwLock::new(PatchStore::default());
+}
+
+/// everything a part plays with, short of the notes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Patch {
+    pub name: String,
+    pub engine: PartEngine,
+    /// the knobs that were turned, the rest stay where the engine starts them.
+    pub knobs: Vec<KnobSetting>,
+    pub stereo: StereoParams,
+    pub bend_range: BendRange,
+}
+
+/// a knob, 1 through 8, and where it was turned to, 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KnobSetting {
+    pub knob: u8,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredPatch {
+    /// bank select MSB * 128 + LSB.
+    pub bank: u16,
+    pub program: u8,
+    #[serde(flatten)]
+    pub patch: Patch,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatchStore {
+    /// sorted by bank, then program.
+    pub patches: Vec<StoredPatch>,
+    /// where this was loaded from and gets saved to, see `Config::path`.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl PatchStore {
+    /// reads `<dir>/patches.toml`. a missing or broken file means no patches.
+    pub fn load(dir: Option<&Path>) -> Self {
+        let Some(path) = dir.map(|dir| dir.join(PATCHES_FILE)) else {
+            return Self::default();
+        };
+
+        let mut store = match std::fs::read_to_string(&path) {
+            Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
+                log::warn!(
+                    "{} is not valid, starting without patches: {e}",
+                    path.display()
+                );
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::warn!(
+                    "could not read {}, starting without patches: {e}",
+                    path.display()
+                );
+                Self::default()
+            }
+        };
+
+        // the file may have been edited by hand. the last patch in it for a bank and program
+        // wins, as if they had been stored in order.
+        store.patches.reverse();
+        store
+            .patches
+            .sort_by_key(|stored| (stored.bank, stored.program));
+        store
+            .patches
+            .dedup_by_key(|stored| (stored.bank, stored.program));
+
+        store.path = Some(path);
+        store
+    }
+
+    /// writes the patches back to where they were loaded from. does nothing without a path.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, bank: u16, program: u8) -> Option<&Patch> {
+        self.find(bank, program)
+            .ok()
+            .map(|i| &self.patches[i].patch)
+    }
+
+    /// stores `patch` at `bank` and `program`, replacing what was there.
+    pub fn store(&mut self, bank: u16, program: u8, patch: Patch) {
+        let stored = StoredPatch {
+            bank,
+            program,
+            patch,
+        };
+
+        match self.find(bank, program) {
+            Ok(i) => self.patches[i] = stored,
+            Err(i) => self.patches.insert(i, stored),
+        }
+    }
+
+    pub fn remove(&mut self, bank: u16, program: u8) {
+        if let Ok(i) = self.find(bank, program) {
+            self.patches.remove(i);
+        }
+    }
+
+    fn find(&self, bank: u16, program: u8) -> Result<usize, usize> {
+        self.patches
+            .binary_search_by_key(&(bank, program), |stored| (stored.bank, stored.program))
+    }
+}
+
+/// the patch stored at `bank` and `program`, if there is one.
+pub fn get(bank: u16, program: u8) -> Option<Patch> {
+    PATCHES.read().ok()?.get(bank, program).cloned()
+}
+
+/// stores `patch` and writes the patches out.
+pub fn store(bank: u16, program: u8, patch: Patch) {
+    let Ok(mut patches) = PATCHES.write() else {
+        return;
+    };
+
+    log::info!("storing {:?} at bank {bank} program {program}", patch.name);
+    patches.store(bank, program, patch);
+
+    if let Err(e) = patches.save() {
+        log::error!("{e}");
+    }
+}
+
+/// removes the patch at `bank` and `program` and writes the patches out.
+pub fn remove(bank: u16, program: u8) {
+    let Ok(mut patches) = PATCHES.write() else {
+        return;
+    };
+
+    patches.remove(bank, program);
+
+    if let Err(e) = patches.save() {
+        log::error!("{e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Patch {
+        Patch {
+            name: name.into(),
+            ..Patch::default()
+        }
+    }
+
+    fn keys(store: &PatchStore) -> Vec<(u16, u8)> {
+        store
+            .patches
+            .iter()
+            .map(|stored| (stored.bank, stored.program))
+            .collect()
+    }
+
+    fn is_sorted(store: &PatchStore) -> bool {
+        keys(store).windows(2).all(|pair| pair[0] < pair[1])
+    }
+
+    #[test]
+    fn load_hand_edited() {
+        let dir = std::env::temp_dir().join(format!("synth-tab-patches-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(PATCHES_FILE),
+            r#"
+            [[patches]]
+            bank = 1
+            program = 5
+            name = "first"
+
+            [[patches]]
+            bank = 0
+            program = 7
+            name = "organ"
+
+            [[patches]]
+            bank = 1
+            program = 5
+            name = "second"
+
+            [[patches]]
+            bank = 0
+            program = 2
+            name = "piano"
+
+            [[patches]]
+            bank = 1
+            program = 5
+            name = "last"
+            "#,
+        )
+        .unwrap();
+
+        let store = PatchStore::load(Some(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(keys(&store), [(0, 2), (0, 7), (1, 5)]);
+        assert_eq!(
+            store.get(1, 5).map(|patch| patch.name.as_str()),
+            Some("last")
+        );
+        assert_eq!(
+            store.get(0, 2).map(|patch| patch.name.as_str()),
+            Some("piano")
+        );
+        assert_eq!(
+            store.get(0, 7).map(|patch| patch.name.as_str()),
+            Some("organ")
+        );
+        assert_eq!(store.path, Some(dir.join(PATCHES_FILE)));
+    }
+
+    #[test]
+    fn load_missing() {
+        assert_eq!(PatchStore::load(None), PatchStore::default());
+
+        let dir = std::env::temp_dir().join("synth-tab-patches-missing");
+        let store = PatchStore::load(Some(&dir));
+
+        assert!(store.patches.is_empty());
+        assert_eq!(store.path, Some(dir.join(PATCHES_FILE)));
+    }
+
+    #[test]
+    fn store_get_remove() {
+        let mut store = PatchStore::default();
+
+        for (bank, program) in [(3, 0), (0, 127), (0, 1), (128, 0), (0, 64), (3, 0)] {
+            store.store(bank, program, named(&format!("{bank}/{program}")));
+            assert!(is_sorted(&store));
+        }
+
+        assert_eq!(keys(&store), [(0, 1), (0, 64), (0, 127), (3, 0), (128, 0)]);
+
+        for (bank, program) in keys(&store) {
+            let name = format!("{bank}/{program}");
+            assert_eq!(store.get(bank, program), Some(&named(&name)));
+        }
+
+        assert_eq!(store.get(0, 2), None);
+        assert_eq!(store.get(1, 1), None);
+
+        store.store(0, 64, named("replaced"));
+        assert_eq!(store.get(0, 64), Some(&named("replaced")));
+        assert_eq!(store.patches.len(), 5);
+
+        store.remove(0, 64);
+        store.remove(5, 5);
+        assert!(is_sorted(&store));
+        assert_eq!(keys(&store), [(0, 1), (0, 127), (3, 0), (128, 0)]);
+        assert_eq!(store.get(0, 64), None);
+        assert_eq!(store.get(3, 0), Some(&named("3/0")));
+    }
+}
@@ -0,0 +1,170 @@
+//! offline rendering of Standard MIDI Files through the synth. drives the same `midi::route` the
+//! live MIDI thread uses so a render matches what the tablet plays.
+
+use crate::midi::route;
+use crate::synth::{SynthHost, TabSynth};
+use midi_control::MidiMessage;
+use midly::{MetaMessage, Smf, Timing, TrackEventKind};
+use std::fmt;
+use std::path::Path;
+use stepper_synth_backend::{CHANNEL_SIZE, SAMPLE_RATE};
+
+/// default tempo of a MIDI file that has no tempo meta event (120 bpm).
+const DEFAULT_US_PER_BEAT: u32 = 500_000;
+
+#[derive(Debug)]
+pub enum RenderError {
+    Io(std::io::Error),
+    Midi(midly::Error),
+    Wav(hound::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read MIDI file: {e}"),
+            Self::Midi(e) => write!(f, "could not parse MIDI file: {e}"),
+            Self::Wav(e) => write!(f, "could not write WAV file: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<std::io::Error> for RenderError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<midly::Error> for RenderError {
+    fn from(e: midly::Error) -> Self {
+        Self::Midi(e)
+    }
+}
+
+impl From<hound::Error> for RenderError {
+    fn from(e: hound::Error) -> Self {
+        Self::Wav(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// seconds of audio rendered after the last event so releases can ring out.
+    pub tail: f64,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { tail: 2.0 }
+    }
+}
+
+/// reads a MIDI file into a list of `(seconds from start, message)` pairs with all tracks merged
+/// and tempo changes applied.
+pub fn load_timeline(path: &Path) -> Result<Vec<(f64, MidiMessage)>, RenderError> {
+    let raw = std::fs::read(path)?;
+    let smf = Smf::parse(&raw)?;
+
+    // (absolute tick, track, index in track, event)
+    let mut events = Vec::new();
+
+    for (track_i, track) in smf.tracks.iter().enumerate() {
+        let mut tick = 0u64;
+
+        for (event_i, event) in track.iter().enumerate() {
+            tick += event.delta.as_int() as u64;
+            events.push((tick, track_i, event_i, event.kind));
+        }
+    }
+
+    events.sort_by_key(|(tick, track_i, event_i, _)| (*tick, *track_i, *event_i));
+
+    let mut timeline = Vec::with_capacity(events.len());
+    let mut us_per_beat = DEFAULT_US_PER_BEAT;
+    let mut last_tick = 0u64;
+    let mut secs = 0.0;
+
+    for (tick, _, _, kind) in events {
+        let ticks = (tick - last_tick) as f64;
+        secs += match smf.header.timing {
+            Timing::Metrical(tpb) => ticks * us_per_beat as f64 / 1_000_000.0 / tpb.as_int() as f64,
+            Timing::Timecode(fps, sub) => ticks / (fps.as_f32() as f64 * sub as f64),
+        };
+        last_tick = tick;
+
+        match kind {
+            TrackEventKind::Meta(MetaMessage::Tempo(tempo)) => us_per_beat = tempo.as_int(),
+            TrackEventKind::Midi { .. } => {
+                let Some(live) = kind.as_live_event() else {
+                    continue;
+                };
+                let mut bytes = Vec::with_capacity(3);
+
+                if let Err(e) = live.write_std(&mut bytes) {
+                    log::error!("could not re-encode MIDI event {kind:?}: {e}");
+                    continue;
+                }
+
+                timeline.push((secs, MidiMessage::from(bytes.as_slice())));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(timeline)
+}
+
+/// renders `midi` through a fresh headless synth into a stereo 32-bit float WAV at `wav`. returns
+/// the number of frames written.
+pub fn render_midi_file(midi: &Path, wav: &Path, opts: RenderOptions) -> Result<u64, RenderError> {
+    let timeline = load_timeline(midi)?;
+    let (mut tab_synth, mut host) = TabSynth::headless();
+
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: SAMPLE_RATE as u32,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(wav, spec)?;
+    let mut left = vec![0.0; CHANNEL_SIZE];
+    let mut right = vec![0.0; CHANNEL_SIZE];
+    let mut written = 0u64;
+
+    // renders up to (not including) frame `end`, a block at a time.
+    let mut render_to = |end: u64, host: &mut SynthHost| -> Result<(), RenderError> {
+        while written < end {
+            let n = ((end - written) as usize).min(left.len());
+            host.render_block(&mut left[..n], &mut right[..n]);
+
+            for (l, r) in left[..n].iter().zip(&right[..n]) {
+                writer.write_sample(*l)?;
+                writer.write_sample(*r)?;
+            }
+
+            written += n as u64;
+        }
+
+        Ok(())
+    };
+
+    let mut end = 0;
+
+    for (secs, msg) in timeline.iter() {
+        end = (secs * SAMPLE_RATE as f64).round() as u64;
+        render_to(end, &mut host)?;
+        route(&mut tab_synth, msg);
+
+        // events that share a frame would otherwise all wait in the command queue for the
+        // next block, and a busy one (a reset and a CC dump at the start) can overflow it.
+        host.apply_pending();
+    }
+
+    end += (opts.tail * SAMPLE_RATE as f64).round() as u64;
+    render_to(end, &mut host)?;
+    writer.finalize()?;
+
+    Ok(end)
+}
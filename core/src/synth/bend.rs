@@ -0,0 +1,121 @@
+//! pitch bend: decoding the 14 bit wheel value and turning it into semitones. the range is part
+//! of the patch, and controllers can change it with RPN 0.
+
+use serde::{Deserialize, Serialize};
+
+/// the wheel's resting value.
+pub const BEND_CENTER: i32 = 8192;
+/// semitones a full bend (1.0 or -1.0) moves the backend engines.
+pub const ENGINE_BEND_SEMITONES: f32 = 2.0;
+/// the most a range can be set to, the largest semitone count RPN 0 can carry.
+pub const MAX_BEND_SEMITONES: f32 = 127.0;
+
+/// how far the wheel bends, in semitones, up and down separately.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BendRange {
+    pub up: f32,
+    pub down: f32,
+}
+
+impl Default for BendRange {
+    /// the General MIDI default, a whole tone each way.
+    fn default() -> Self {
+        Self { up: 2.0, down: 2.0 }
+    }
+}
+
+impl BendRange {
+    /// the same range both ways, as RPN 0 sets it.
+    pub fn symmetric(semitones: f32) -> Self {
+        let semitones = semitones.clamp(0.0, MAX_BEND_SEMITONES);
+
+        Self {
+            up: semitones,
+            down: semitones,
+        }
+    }
+
+    /// semitones for a bend of -1 to 1.
+    pub fn semitones(&self, bend: f32) -> f32 {
+        if bend >= 0.0 {
+            bend * self.up
+        } else {
+            bend * self.down
+        }
+    }
+}
+
+/// the 14 bit value from a pitch bend message as -1 to 1. 8192 is center, and the two halves
+/// are scaled separately so both ends of the wheel reach the full range.
+pub fn decode(lsb: u8, msb: u8) -> f32 {
+    let value = ((msb as i32 & 0x7f) << 7 | (lsb as i32 & 0x7f)) - BEND_CENTER;
+
+    if value >= 0 {
+        value as f32 / (BEND_CENTER - 1) as f32
+    } else {
+        value as f32 / BEND_CENTER as f32
+    }
+}
+
+/// zeroes bends smaller than `deadzone` (out of 1) and rescales the rest so the wheel doesn't
+/// jump as it leaves the deadzone.
+pub fn apply_deadzone(bend: f32, deadzone: f32) -> f32 {
+    let deadzone = deadzone.clamp(0.0, 0.99);
+
+    if bend.abs() <= deadzone {
+        0.0
+    } else {
+        bend.signum() * (bend.abs() - deadzone) / (1.0 - deadzone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_ends_and_center() {
+        assert_eq!(decode(0x00, 0x00), -1.0);
+        assert_eq!(decode(0x00, 0x40), 0.0);
+        assert_eq!(decode(0x7f, 0x7f), 1.0);
+
+        assert!(decode(0x7f, 0x3f) < 0.0);
+        assert!(decode(0x01, 0x40) > 0.0);
+    }
+
+    #[test]
+    fn decode_ignores_high_bits() {
+        assert_eq!(decode(0x80, 0xc0), decode(0x00, 0x40));
+    }
+
+    #[test]
+    fn deadzone() {
+        assert_eq!(apply_deadzone(0.1, 0.1), 0.0);
+        assert_eq!(apply_deadzone(-0.1, 0.1), 0.0);
+        assert_eq!(apply_deadzone(1.0, 0.1), 1.0);
+        assert_eq!(apply_deadzone(-1.0, 0.1), -1.0);
+        assert!((apply_deadzone(0.55, 0.1) - 0.5).abs() < 1.0e-6);
+        assert!((apply_deadzone(-0.55, 0.1) + 0.5).abs() < 1.0e-6);
+
+        // just past the edge is just past 0, not a jump.
+        assert!(apply_deadzone(0.1001, 0.1) < 0.001);
+
+        assert_eq!(apply_deadzone(0.3, 0.0), 0.3);
+        // a deadzone of the whole wheel still leaves the very end.
+        assert_eq!(apply_deadzone(1.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn range() {
+        let range = BendRange {
+            up: 2.0,
+            down: 12.0,
+        };
+        assert_eq!(range.semitones(0.5), 1.0);
+        assert_eq!(range.semitones(-0.5), -6.0);
+
+        assert_eq!(BendRange::symmetric(200.0).up, MAX_BEND_SEMITONES);
+        assert_eq!(BendRange::symmetric(-3.0).down, 0.0);
+    }
+}
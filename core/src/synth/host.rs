@@ -0,0 +1,451 @@
+//! the audio thread's side of the synth. `SynthHost` owns the parts outright; everything else
+//! talks to it through `SynthCmd`s on a wait-free ring buffer, so the audio callback never
+//! waits on a lock held by the UI or MIDI threads. parts are built elsewhere and handed over
+//! whole through a `PartLoader`.
+
+use super::bend::BendRange;
+use super::multi::MAX_PARTS;
+use super::part::Part;
+use super::stereo::StereoParam;
+use midi_control::MidiMessage;
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::fmt;
+use std::time::{Duration, Instant};
+use stepper_synth_backend::{pygame_coms::SynthEngineType, CHANNEL_SIZE, SAMPLE_RATE};
+
+/// how many commands can be waiting for the audio thread before new ones are dropped.
+pub const COMMAND_QUEUE_SIZE: usize = 1024;
+/// the most frames rendered between checks of the command queue. timestamped commands split
+/// blocks further so they land on their exact frame.
+pub const SUB_BLOCK_SIZE: usize = 32;
+/// how many part swaps can be waiting.
+const SWAP_QUEUE_SIZE: usize = MAX_PARTS * 2;
+/// room for every part that can exist at once: the ones playing, the ones ringing out, and the
+/// ones waiting to be swapped in. the loader frees retired parts before every swap, so the
+/// queue back never fills.
+const RETIRED_QUEUE_SIZE: usize = SWAP_QUEUE_SIZE + MAX_PARTS * 2;
+
+/// a change to the synth, applied by the audio thread at the start of the next block.
+#[derive(Debug, Clone)]
+pub enum SynthCmd {
+    NoteOn {
+        key: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        key: u8,
+    },
+    /// the wheel position, -1 to 1. how far that bends is up to the `BendRange`.
+    Bend(f32),
+    Unbend,
+    BendRange(BendRange),
+    /// `knob` is 1 through 8.
+    Knob {
+        knob: u8,
+        value: f32,
+    },
+    VolumeSwell(f32),
+    /// channel pressure (aftertouch), 0 to 1.
+    Pressure(f32),
+    /// polyphonic key pressure, 0 to 1.
+    PolyPressure {
+        key: u8,
+        value: f32,
+    },
+    /// pedals, `true` is down. sustain holds every note let go while it is down, sostenuto
+    /// only the notes that were down when it was pressed, and soft plays new notes quieter.
+    Sustain(bool),
+    Sostenuto(bool),
+    Soft(bool),
+    /// a note on an MPE member channel, 1 through 16. each channel has an engine of its own, so
+    /// the channel's expression moves only the notes on it.
+    MemberNoteOn {
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    },
+    MemberNoteOff {
+        channel: u8,
+        key: u8,
+    },
+    /// a member channel's bend, -1 to 1. adds to the master channel's.
+    MemberBend {
+        channel: u8,
+        bend: f32,
+    },
+    /// a member channel's pressure, 0 to 1. swells its notes like channel pressure does.
+    MemberPressure {
+        channel: u8,
+        value: f32,
+    },
+    /// sets a knob on one member channel's engine only, for timbre.
+    MemberKnob {
+        channel: u8,
+        knob: u8,
+        value: f32,
+    },
+    /// the bend range of every member channel.
+    MemberBendRange(BendRange),
+    /// lets go of every held key, pedals still hold what they are holding.
+    AllNotesOff,
+    /// releases every note and mutes the release tails.
+    AllSoundOff,
+    /// puts bend, pressure, and the mod wheel back to rest.
+    ResetControllers,
+    /// `true` for mono mode (one note at a time), `false` for poly.
+    Mono(bool),
+    /// the part's volume in the mix, 0 to 1.
+    Level(f32),
+    /// handed to engines that interpret MIDI themselves (the wavetable engine).
+    Midi(MidiMessage),
+    Stereo(StereoParam),
+}
+
+impl SynthCmd {
+    /// true for the commands that let go of notes, which also reach a part that is ringing out.
+    fn releases(&self) -> bool {
+        matches!(
+            self,
+            Self::NoteOn { velocity: 0, .. }
+                | Self::NoteOff { .. }
+                | Self::MemberNoteOn { velocity: 0, .. }
+                | Self::MemberNoteOff { .. }
+                | Self::Sustain(false)
+                | Self::Sostenuto(false)
+                | Self::AllNotesOff
+                | Self::AllSoundOff
+        )
+    }
+}
+
+/// a command, the part it is for, and when it happened. timestamped commands are played a fixed
+/// delay after their timestamp, on the matching frame, so they keep their spacing however the
+/// audio callbacks fall. `None` is applied as soon as possible.
+#[derive(Debug, Clone)]
+pub struct Scheduled {
+    pub at: Option<Instant>,
+    /// 0 through 15, commands for a part that isn't loaded are dropped.
+    pub part: u8,
+    pub cmd: SynthCmd,
+}
+
+impl From<SynthCmd> for Scheduled {
+    fn from(cmd: SynthCmd) -> Self {
+        Self {
+            at: None,
+            part: 0,
+            cmd,
+        }
+    }
+}
+
+/// a part to put in slot `index`, `None` to empty it.
+struct PartSwap {
+    index: usize,
+    part: Option<Box<Part>>,
+    /// keep playing the part it replaces until its held notes have rung out.
+    ring_out: bool,
+}
+
+/// hands built parts to a running host. parts are built and freed on the loader's thread, the
+/// audio thread only moves boxes around.
+pub struct PartLoader {
+    swaps: Producer<PartSwap>,
+    /// parts the host swapped out, waiting to be freed here.
+    retired: Consumer<Box<Part>>,
+}
+
+impl PartLoader {
+    /// puts `part` in slot `index` from the next block on, replacing what was there. returns
+    /// false if the host has too many swaps waiting already.
+    pub fn load(&mut self, index: usize, part: Option<Part>) -> bool {
+        self.swap(index, part, false)
+    }
+
+    /// like `load`, but the part it replaces plays on until the notes held on it are let go and
+    /// have rung out. commands other than note offs and pedal releases only reach the new part.
+    pub fn load_ringing_out(&mut self, index: usize, part: Part) -> bool {
+        self.swap(index, Some(part), true)
+    }
+
+    fn swap(&mut self, index: usize, part: Option<Part>, ring_out: bool) -> bool {
+        while self.retired.pop().is_ok() {}
+
+        self.swaps
+            .push(PartSwap {
+                index,
+                part: part.map(Box::new),
+                ring_out,
+            })
+            .is_ok()
+    }
+}
+
+impl fmt::Debug for PartLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PartLoader").finish_non_exhaustive()
+    }
+}
+
+pub struct SynthHost {
+    /// indexed by part, mixed together. the first is always there unless swapped out by hand.
+    parts: [Option<Box<Part>>; MAX_PARTS],
+    /// parts swapped out by a patch change, mixed in until their notes have rung out.
+    outgoing: [Option<Box<Part>>; MAX_PARTS],
+    commands: Consumer<Scheduled>,
+    swaps: Consumer<PartSwap>,
+    retired: Producer<Box<Part>>,
+    /// the other ends of `swaps` and `retired`, until someone takes them.
+    loader: Option<PartLoader>,
+    /// scratch buffers, sized up front so the audio thread never allocates.
+    left: Vec<f32>,
+    right: Vec<f32>,
+    /// frames of `left`/`right` rendered, and how many of those have been played.
+    filled: usize,
+    played: usize,
+    /// engine frames per output frame. the engine always runs at `SAMPLE_RATE`, other device
+    /// rates are linearly resampled.
+    step: f64,
+    /// how far between `prev` and `next` the next output frame falls.
+    phase: f64,
+    prev: [f32; 2],
+    next: [f32; 2],
+    /// engine frames rendered since the host was made.
+    rendered: u64,
+    /// when the last audio callback started and the engine frame it started playing, `None`
+    /// until the first callback. ties timestamps to frames.
+    clock: Option<(Instant, u64)>,
+    /// how long after its timestamp a command is played. one device buffer, so anything that
+    /// arrived while the last buffer was playing still has its frame ahead of it.
+    delay: Duration,
+}
+
+impl SynthHost {
+    /// makes a host playing one part on `engine`, and the producer end of its command queue.
+    pub fn new(engine: SynthEngineType) -> (Self, Producer<Scheduled>) {
+        let (producer, commands) = RingBuffer::new(COMMAND_QUEUE_SIZE);
+        let (swap_producer, swaps) = RingBuffer::new(SWAP_QUEUE_SIZE);
+        let (retired, retired_consumer) = RingBuffer::new(RETIRED_QUEUE_SIZE);
+        let mut parts: [Option<Box<Part>>; MAX_PARTS] = std::array::from_fn(|_| None);
+        parts[0] = Some(Box::new(Part::new(engine)));
+
+        let host = Self {
+            parts,
+            outgoing: std::array::from_fn(|_| None),
+            commands,
+            swaps,
+            retired,
+            loader: Some(PartLoader {
+                swaps: swap_producer,
+                retired: retired_consumer,
+            }),
+            left: vec![0.0; CHANNEL_SIZE],
+            right: vec![0.0; CHANNEL_SIZE],
+            filled: 0,
+            played: 0,
+            step: 1.0,
+            phase: 0.0,
+            prev: [0.0; 2],
+            next: [0.0; 2],
+            rendered: 0,
+            clock: None,
+            delay: Duration::ZERO,
+        };
+
+        (host, producer)
+    }
+
+    /// the loader for this host's parts. there is only one, `None` once it has been taken.
+    pub fn take_loader(&mut self) -> Option<PartLoader> {
+        self.loader.take()
+    }
+
+    /// puts in the parts that have been loaded. the ones they replace ring out or go back to
+    /// the loader.
+    fn take_swaps(&mut self) {
+        while let Ok(swap) = self.swaps.pop() {
+            let Some(slot) = self.parts.get_mut(swap.index) else {
+                continue;
+            };
+
+            let Some(old) = std::mem::replace(slot, swap.part) else {
+                continue;
+            };
+
+            let old = match swap.ring_out {
+                // only the last part swapped out rings out.
+                true => self.outgoing[swap.index].replace(old),
+                false => Some(old),
+            };
+
+            if let Some(old) = old {
+                self.retire(old);
+            }
+        }
+    }
+
+    fn retire(&mut self, part: Box<Part>) {
+        // can't be full, see `RETIRED_QUEUE_SIZE`.
+        let _ = self.retired.push(part);
+    }
+
+    fn apply(&mut self, scheduled: Scheduled) {
+        // a part loaded before this command was sent is in the swap queue by now.
+        self.take_swaps();
+        let index = scheduled.part as usize;
+
+        if scheduled.cmd.releases() {
+            if let Some(Some(part)) = self.outgoing.get_mut(index) {
+                part.apply(scheduled.cmd.clone());
+            }
+        }
+
+        if let Some(Some(part)) = self.parts.get_mut(index) {
+            part.apply(scheduled.cmd);
+        }
+    }
+
+    /// applies every command that is waiting, timestamps or not. never blocks.
+    pub fn apply_pending(&mut self) {
+        while let Ok(scheduled) = self.commands.pop() {
+            self.apply(scheduled);
+        }
+
+        self.take_swaps();
+    }
+
+    /// the engine frame a command stamped `at` plays on, `None` for right away.
+    fn frame_of(&self, at: Option<Instant>) -> Option<u64> {
+        let (callback, frame) = self.clock?;
+        let due = at? + self.delay;
+        let offset = match due.checked_duration_since(callback) {
+            Some(ahead) => ahead.as_secs_f64(),
+            None => -callback.duration_since(due).as_secs_f64(),
+        };
+
+        Some((frame as f64 + offset * SAMPLE_RATE as f64).max(0.0) as u64)
+    }
+
+    /// applies the waiting commands that are due by the current frame. returns the frame of the
+    /// first one that isn't due yet. commands are taken in order, so one that is early holds up
+    /// the ones behind it.
+    fn apply_due(&mut self) -> Option<u64> {
+        loop {
+            let due = match self.commands.peek() {
+                Ok(scheduled) => self.frame_of(scheduled.at),
+                Err(_) => return None,
+            };
+
+            if let Some(frame) = due.filter(|frame| *frame > self.rendered) {
+                return Some(frame);
+            }
+
+            if let Ok(scheduled) = self.commands.pop() {
+                self.apply(scheduled);
+            }
+        }
+    }
+
+    /// sizes the scratch buffers for blocks of up to `frames` and sets the device rate. call
+    /// before handing the host to the audio thread.
+    pub fn prepare(&mut self, frames: usize, sample_rate: usize) {
+        let frames = frames.max(SUB_BLOCK_SIZE);
+        self.left.resize(frames, 0.0);
+        self.right.resize(frames, 0.0);
+        self.filled = 0;
+        self.played = 0;
+        self.step = SAMPLE_RATE as f64 / sample_rate.max(1) as f64;
+        self.phase = 0.0;
+        self.clock = None;
+        self.delay = Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64);
+    }
+
+    /// fills `left` and `right` (the same length), applying commands as their frames come up.
+    pub fn render_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let len = left.len().min(right.len());
+        let mut pos = 0;
+        self.take_swaps();
+
+        while pos < len {
+            let mut end = (pos + SUB_BLOCK_SIZE).min(len);
+
+            if let Some(frame) = self.apply_due() {
+                end = end.min(pos + (frame - self.rendered) as usize);
+            }
+
+            let (left, right) = (&mut left[pos..end], &mut right[pos..end]);
+            left.fill(0.0);
+            right.fill(0.0);
+
+            for part in self.parts.iter_mut().flatten() {
+                part.render(left, right);
+            }
+
+            for index in 0..MAX_PARTS {
+                let Some(part) = self.outgoing[index].as_mut() else {
+                    continue;
+                };
+
+                part.render(left, right);
+
+                if part.is_idle() {
+                    if let Some(part) = self.outgoing[index].take() {
+                        self.retire(part);
+                    }
+                }
+            }
+
+            self.rendered += (end - pos) as u64;
+            pos = end;
+        }
+    }
+
+    /// the next engine frame, rendering another block when the last one has been played.
+    fn pull(&mut self) -> [f32; 2] {
+        if self.played == self.filled {
+            let mut left = std::mem::take(&mut self.left);
+            let mut right = std::mem::take(&mut self.right);
+            self.render_block(&mut left, &mut right);
+            self.left = left;
+            self.right = right;
+            self.filled = self.left.len();
+            self.played = 0;
+        }
+
+        let frame = [self.left[self.played], self.right[self.played]];
+        self.played += 1;
+
+        frame
+    }
+
+    /// fills `data`, an interleaved buffer of `channels` channels at the rate given to
+    /// `prepare`. mono devices get both sides summed, channels past the second are left silent.
+    pub fn render(&mut self, data: &mut [f32], channels: usize) {
+        let playing = self.rendered - (self.filled - self.played) as u64;
+        self.clock = Some((Instant::now(), playing));
+
+        for samples in data.chunks_mut(channels.max(1)) {
+            while self.phase >= 0.0 {
+                self.prev = self.next;
+                self.next = self.pull();
+                self.phase -= 1.0;
+            }
+
+            // `phase` is in [-1, 0), so this is `prev` + (`next` - `prev`) * fraction.
+            let t = (self.phase + 1.0) as f32;
+            let l = self.prev[0] + (self.next[0] - self.prev[0]) * t;
+            let r = self.prev[1] + (self.next[1] - self.prev[1]) * t;
+            self.phase += self.step;
+
+            match samples {
+                [mono] => *mono = (l + r) * 0.5,
+                [out_l, out_r, rest @ ..] => {
+                    *out_l = l;
+                    *out_r = r;
+                    rest.fill(0.0);
+                }
+                [] => {}
+            }
+        }
+    }
+}
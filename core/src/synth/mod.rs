@@ -0,0 +1,540 @@
+use crate::audio::latency::Latency;
+use crate::audio::{AudioError, AudioParams, BackendConfig};
+use crate::config::Config;
+use crate::patches::{self, KnobSetting, Patch};
+use midi_control::MidiMessage;
+use rtrb::Producer;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{sleep, spawn, JoinHandle};
+use std::time::{Duration, Instant};
+use stepper_synth_backend::pygame_coms::SynthEngineType;
+
+pub mod bend;
+pub mod host;
+pub mod multi;
+pub mod output;
+pub mod part;
+pub mod stereo;
+
+pub use bend::BendRange;
+use part::Part;
+
+pub use host::{PartLoader, Scheduled, SynthCmd, SynthHost};
+pub use multi::{MultiConfig, PartConfig, PartEngine, MAX_PARTS};
+pub use output::{AudioStatus, Output};
+pub use stereo::{StereoParam, StereoParams};
+
+/// what a loaded part was built with and last told, kept so the UI can draw it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartState {
+    pub engine: PartEngine,
+    pub stereo: StereoParams,
+    /// the bank the last program change was recalled from, see `midi::bank`.
+    pub bank: u16,
+    /// the last program change received, 0 through 127.
+    pub program: u8,
+    /// the patch's bend range, as last sent to the host.
+    pub bend_range: BendRange,
+    pub level: f32,
+    /// knobs 1 through 8 as last turned, `None` for the ones left where the engine started them.
+    pub knobs: [Option<f32>; 8],
+    /// built with the MPE member engines, see `Part::with_members`. only ever the first part.
+    pub members: bool,
+}
+
+impl PartState {
+    fn new(engine: PartEngine) -> Self {
+        Self {
+            engine,
+            stereo: StereoParams::default(),
+            bank: 0,
+            program: 0,
+            bend_range: BendRange::default(),
+            level: 1.0,
+            knobs: [None; 8],
+            members: false,
+        }
+    }
+}
+
+/// the control side of the synth. the parts themselves live in a `SynthHost` on the audio
+/// thread; this hands it commands and remembers what the UI needs to draw. commands go to one
+/// part at a time: the one the router is playing while it routes a message, otherwise the one
+/// picked in the UI.
+#[derive(Debug)]
+pub struct TabSynth {
+    commands: Mutex<Producer<Scheduled>>,
+    loader: PartLoader,
+    /// stamped on everything sent, set by the MIDI router while it routes a message.
+    timestamp: Option<Instant>,
+    /// the part the router is playing, see `set_part`.
+    routing: Option<usize>,
+    /// the part the UI edits and the on-screen keyboard plays, always loaded.
+    selected: usize,
+    /// `None` for parts that aren't loaded. the first always is.
+    parts: [Option<PartState>; MAX_PARTS],
+    /// the bend range of the MPE member engines, on the first part while MPE has a zone.
+    member_range: BendRange,
+    /// the device and the host feeding it, `None` when samples are pulled by hand.
+    output: Option<Output>,
+}
+
+impl TabSynth {
+    /// makes the synth without an output device. the caller drives the returned host by hand,
+    /// used by the offline renderer.
+    pub fn headless() -> (Self, SynthHost) {
+        // let (host, commands) = SynthHost::new(SynthEngineType::SubSynth);
+        // let (host, commands) = SynthHost::new(SynthEngineType::MidiOut);
+        let (mut host, commands) = SynthHost::new(PartEngine::WaveTable.engine_type());
+        let loader = host.take_loader().expect("a new host has its loader");
+        let mut parts = [None; MAX_PARTS];
+        parts[0] = Some(PartState::new(PartEngine::WaveTable));
+
+        let tab_synth = Self {
+            commands: Mutex::new(commands),
+            loader,
+            timestamp: None,
+            routing: None,
+            selected: 0,
+            parts,
+            member_range: BendRange::default(),
+            output: None,
+        };
+
+        (tab_synth, host)
+    }
+
+    /// makes the synth and starts it on `config`. an output that won't start is retried in the
+    /// background (see `spawn_watchdog`) rather than taking the app down with it.
+    pub fn new(config: &BackendConfig, params: AudioParams) -> Self {
+        let (mut tab_synth, host) = Self::headless();
+        tab_synth.output = Some(Output::new(host, config.build(), params));
+
+        tab_synth
+    }
+
+    pub fn audio_params(&self) -> AudioParams {
+        self.output.as_ref().map(Output::params).unwrap_or_default()
+    }
+
+    /// restarts the output with new settings. the patch and any held notes carry over. if the
+    /// device won't take them, the old settings are put back and the error is returned.
+    pub fn set_audio_params(&mut self, params: AudioParams) -> Result<(), AudioError> {
+        match self.output.as_mut() {
+            Some(output) => output.set_params(params),
+            None => Ok(()),
+        }
+    }
+
+    /// `None` when headless.
+    pub fn audio_status(&self) -> Option<&AudioStatus> {
+        self.output.as_ref().map(Output::status)
+    }
+
+    pub fn retry_audio(&mut self) {
+        if let Some(output) = self.output.as_mut() {
+            output.retry();
+        }
+    }
+
+    /// what latency the current settings should give and what the device is really doing.
+    pub fn latency(&self) -> Latency {
+        match self.output.as_ref() {
+            Some(output) => output.latency(),
+            None => Latency {
+                theoretical: AudioParams::default().buffer_duration(),
+                measured: None,
+                callback_period: None,
+            },
+        }
+    }
+
+    /// the part commands go to right now.
+    pub fn part(&self) -> usize {
+        self.routing.unwrap_or(self.selected)
+    }
+
+    /// sends what follows to `part` while the router plays a message on it. `None` goes back to
+    /// the part picked in the UI.
+    pub fn set_part(&mut self, part: Option<usize>) {
+        self.routing = part;
+    }
+
+    pub fn selected_part(&self) -> usize {
+        self.selected
+    }
+
+    /// picks the part the UI edits. parts that aren't loaded can't be picked.
+    pub fn select_part(&mut self, part: usize) {
+        if self.parts.get(part).is_some_and(Option::is_some) {
+            self.selected = part;
+        }
+    }
+
+    /// the state of `part`, `None` if it isn't loaded.
+    pub fn part_state(&self, part: usize) -> Option<&PartState> {
+        self.parts.get(part)?.as_ref()
+    }
+
+    /// the state of the part commands go to, falling back to the first part if that one isn't
+    /// loaded.
+    fn state(&self) -> &PartState {
+        self.part_state(self.part())
+            .or(self.parts[0].as_ref())
+            .expect("the first part is always loaded")
+    }
+
+    fn state_mut(&mut self) -> Option<&mut PartState> {
+        let part = self.part();
+        self.parts.get_mut(part)?.as_mut()
+    }
+
+    pub fn engine_type(&self) -> SynthEngineType {
+        self.state().engine.engine_type()
+    }
+
+    pub fn program(&self) -> u8 {
+        self.state().program
+    }
+
+    pub fn set_program(&mut self, program: u8) {
+        if let Some(state) = self.state_mut() {
+            state.program = program & 0x7f;
+        }
+    }
+
+    pub fn bend_range(&self) -> BendRange {
+        self.state().bend_range
+    }
+
+    pub fn set_bend_range(&mut self, range: BendRange) {
+        if let Some(state) = self.state_mut() {
+            state.bend_range = range;
+        }
+
+        self.send(SynthCmd::BendRange(range));
+    }
+
+    pub fn stereo(&self) -> &StereoParams {
+        &self.state().stereo
+    }
+
+    pub fn set_stereo(&mut self, param: StereoParam) {
+        if let Some(state) = self.state_mut() {
+            state.stereo.set(param);
+        }
+
+        self.send(SynthCmd::Stereo(param));
+    }
+
+    /// turns knob `knob` (1 through 8) of the current part to `value`, 0 to 1.
+    pub fn set_knob(&mut self, knob: u8, value: f32) {
+        let slot = self
+            .state_mut()
+            .and_then(|state| state.knobs.get_mut(knob.wrapping_sub(1) as usize));
+
+        if let Some(slot) = slot {
+            *slot = Some(value);
+        }
+
+        self.send(SynthCmd::Knob { knob, value });
+    }
+
+    pub fn member_range(&self) -> BendRange {
+        self.member_range
+    }
+
+    /// sets the bend range of the MPE member engines, which are on the first part.
+    pub fn set_member_range(&mut self, range: BendRange) {
+        self.member_range = range;
+        self.send_to(0, SynthCmd::MemberBendRange(range));
+    }
+
+    /// builds, rebuilds, and drops parts to match `config.multi` and the parts keyboard zones
+    /// play, and sets their levels. a part is only rebuilt when its engine changes, or for the
+    /// first part, when MPE gains or loses its zones; a new one starts on its program with the
+    /// bend ranges from `config`.
+    pub fn load_parts(&mut self, config: &Config) {
+        for index in 0..MAX_PARTS {
+            // outside of multi mode, parts past the first are only there for keyboard zones.
+            let wanted = config.multi.part(index).or_else(|| {
+                config
+                    .zones
+                    .targets(index)
+                    .then(|| config.multi.parts.get(index).copied())
+                    .flatten()
+            });
+
+            match (wanted, self.parts[index]) {
+                (None, None) => continue,
+                (None, Some(_)) => {
+                    if self.loader.load(index, None) {
+                        self.parts[index] = None;
+                    }
+
+                    continue;
+                }
+                (Some(part), Some(state))
+                    if part.engine == state.engine
+                        && state.members == Self::wants_members(index, config) => {}
+                (Some(part), _) => self.load_part(index, part, config),
+            }
+
+            if let Some(part) = wanted {
+                self.set_level(index, part.level);
+            }
+        }
+
+        if self.parts[self.selected].is_none() {
+            self.selected = 0;
+        }
+    }
+
+    /// drops the parts from `from` on and loads them again, for when parts have moved up a
+    /// slot. the first part stays.
+    pub fn reload_parts(&mut self, from: usize, config: &Config) {
+        for index in from.max(1)..MAX_PARTS {
+            if self.parts[index].is_some() && self.loader.load(index, None) {
+                self.parts[index] = None;
+            }
+        }
+
+        self.load_parts(config);
+    }
+
+    /// true if part `index` should carry the MPE member engines: the first part, while MPE has
+    /// a zone.
+    fn wants_members(index: usize, config: &Config) -> bool {
+        index == 0 && config.mpe.has_zone()
+    }
+
+    /// a fresh part for slot `index`, with the MPE member engines if `members` is set.
+    fn build_part(index: usize, engine: PartEngine, members: bool) -> Part {
+        match index {
+            0 if members => Part::with_members(engine.engine_type()),
+            _ => Part::new(engine.engine_type()),
+        }
+    }
+
+    /// builds part `index` fresh, on the patch stored at its bank and program if there is one
+    /// for its engine.
+    fn load_part(&mut self, index: usize, part: PartConfig, config: &Config) {
+        let members = Self::wants_members(index, config);
+
+        if !self
+            .loader
+            .load(index, Some(Self::build_part(index, part.engine, members)))
+        {
+            log::warn!("too many parts waiting to load, part {} dropped", index + 1);
+            return;
+        }
+
+        log::info!("part {} loaded with {}", index + 1, part.engine);
+        self.parts[index] = Some(PartState {
+            bank: part.bank,
+            members,
+            ..PartState::new(part.engine)
+        });
+
+        let routing = self.routing.replace(index);
+        self.set_bend_range(config.bend.range);
+
+        if index == 0 {
+            self.set_member_range(self.member_range);
+        }
+
+        match patches::get(part.bank, part.program).filter(|patch| patch.engine == part.engine) {
+            Some(patch) => self.apply_patch(part.program, &patch),
+            None if part.program != 0 => self.change_program(part.program),
+            None => {}
+        }
+
+        self.routing = routing;
+    }
+
+    /// sets `part`'s volume in the mix.
+    pub fn set_level(&mut self, part: usize, level: f32) {
+        let Some(state) = self.parts.get_mut(part).and_then(Option::as_mut) else {
+            return;
+        };
+
+        if state.level != level {
+            state.level = level;
+            self.send_to(part, SynthCmd::Level(level));
+        }
+    }
+
+    /// switches the current part to `program`, as a program change on its channel would.
+    pub fn change_program(&mut self, program: u8) {
+        self.set_program(program);
+
+        // the wavetable engine handles programs itself, the channel doesn't matter to it.
+        if matches!(self.engine_type(), SynthEngineType::WaveTable) {
+            let msg = MidiMessage::from(&[0xc0, program & 0x7f][..]);
+            self.send(SynthCmd::Midi(msg));
+        }
+    }
+
+    /// switches the current part to `program` in `bank`, as a program change after a bank
+    /// select would. a patch stored there gets a part of its own, built fresh, while the old one
+    /// rings out under the notes still held on it; otherwise the part's engine gets the program
+    /// change. returns the engine the part is on now, `None` if it isn't loaded.
+    pub fn recall(&mut self, bank: u16, program: u8) -> Option<PartEngine> {
+        let index = self.part();
+        let state = self.part_state(index).copied()?;
+
+        let Some(patch) = patches::get(bank, program) else {
+            if let Some(state) = self.state_mut() {
+                state.bank = bank;
+            }
+
+            self.change_program(program);
+            return Some(state.engine);
+        };
+
+        let part = Self::build_part(index, patch.engine, state.members);
+
+        if !self.loader.load_ringing_out(index, part) {
+            log::warn!(
+                "too many parts waiting to load, patch {:?} dropped",
+                patch.name
+            );
+            return Some(state.engine);
+        }
+
+        log::info!(
+            "part {} recalled {:?} from bank {bank} program {program}",
+            index + 1,
+            patch.name
+        );
+
+        // the level belongs to the mix, not the patch.
+        self.parts[index] = Some(PartState {
+            bank,
+            level: state.level,
+            members: state.members,
+            ..PartState::new(patch.engine)
+        });
+        self.send(SynthCmd::Level(state.level));
+
+        if index == 0 {
+            self.set_member_range(self.member_range);
+        }
+
+        self.apply_patch(program, &patch);
+
+        Some(patch.engine)
+    }
+
+    /// sets the current part up the way `patch` was stored, on top of the engine's own
+    /// `program`.
+    fn apply_patch(&mut self, program: u8, patch: &Patch) {
+        self.change_program(program);
+        self.set_bend_range(patch.bend_range);
+
+        let stereo = patch.stereo;
+
+        for param in [
+            StereoParam::Pan(stereo.pan),
+            StereoParam::Width(stereo.width),
+            StereoParam::Spread(stereo.spread),
+            StereoParam::Unison(stereo.unison),
+        ] {
+            self.set_stereo(param);
+        }
+
+        for setting in &patch.knobs {
+            self.set_knob(setting.knob, setting.value);
+        }
+    }
+
+    /// the current part's sound as a patch called `name`, for storing.
+    pub fn patch(&self, name: String) -> Patch {
+        let state = self.state();
+        let knobs = state
+            .knobs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, value)| {
+                value.map(|value| KnobSetting {
+                    knob: i as u8 + 1,
+                    value,
+                })
+            })
+            .collect();
+
+        Patch {
+            name,
+            engine: state.engine,
+            knobs,
+            stereo: state.stereo,
+            bend_range: state.bend_range,
+        }
+    }
+
+    /// stamps what is sent from here on with `time`, so the audio thread plays it with the
+    /// spacing it arrived with. `None` goes back to playing things as soon as possible.
+    pub fn set_timestamp(&mut self, time: Option<Instant>) {
+        self.timestamp = time;
+    }
+
+    /// queues `cmd` for the current part, see `part`.
+    pub fn send(&self, cmd: SynthCmd) {
+        self.send_to(self.part(), cmd);
+    }
+
+    /// queues `cmd` for the audio thread. if the queue is full the command is dropped rather
+    /// than making anyone wait.
+    pub fn send_to(&self, part: usize, cmd: SynthCmd) {
+        let Ok(mut commands) = self.commands.lock() else {
+            return;
+        };
+
+        if let Err(e) = commands.push(Scheduled {
+            at: self.timestamp,
+            part: part as u8,
+            cmd,
+        }) {
+            log::warn!("synth command queue is full, dropped: {e:?}");
+        }
+    }
+
+    pub fn play(&mut self, note: u8, velocity: u8) {
+        log::debug!("playing note {note}");
+        self.send(SynthCmd::NoteOn {
+            key: note,
+            velocity,
+        });
+    }
+
+    pub fn stop(&mut self, note: u8) {
+        log::debug!("stopping note {note}");
+        self.send(SynthCmd::NoteOff { key: note });
+    }
+}
+
+/// how often the watchdog looks at the audio output.
+const WATCHDOG_PERIOD: Duration = Duration::from_millis(250);
+
+/// spawns the thread that notices lost audio devices and restarts failed ones.
+pub fn spawn_watchdog(synth: Arc<RwLock<TabSynth>>) -> JoinHandle<()> {
+    spawn(move || loop {
+        sleep(WATCHDOG_PERIOD);
+
+        if let Ok(mut tab_synth) = synth.write() {
+            if let Some(output) = tab_synth.output.as_mut() {
+                output.check();
+            }
+        }
+    })
+}
+
+// #[unsafe(no_mangle)]
+pub fn make_synth(config: &BackendConfig, params: AudioParams) -> TabSynth {
+    // let synth = Synth::new();
+    // let sequencer = Arc::new(Mutex::new(SequencerIntake::new(synth)));
+
+    // synth.play(42, 127);
+
+    TabSynth::new(config, params)
+}
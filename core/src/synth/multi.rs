@@ -0,0 +1,148 @@
+//! multi-timbral mode: up to 16 parts, each with its own engine and program, played from the
+//! MIDI channel given to it and mixed together. part 1 is the synth as it is outside of multi
+//! mode, and the only part MPE zones play on.
+
+use crate::midi::channel::channel_of;
+use midi_control::MidiMessage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use stepper_synth_backend::pygame_coms::SynthEngineType;
+
+/// one part per MIDI channel at most.
+pub const MAX_PARTS: usize = 16;
+
+/// the engines a part can be built on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PartEngine {
+    #[default]
+    WaveTable,
+    SubSynth,
+}
+
+impl PartEngine {
+    pub const ALL: [PartEngine; 2] = [PartEngine::WaveTable, PartEngine::SubSynth];
+
+    pub fn engine_type(&self) -> SynthEngineType {
+        match self {
+            Self::WaveTable => SynthEngineType::WaveTable,
+            Self::SubSynth => SynthEngineType::SubSynth,
+        }
+    }
+}
+
+impl fmt::Display for PartEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WaveTable => write!(f, "Wavetable"),
+            Self::SubSynth => write!(f, "Subtractive"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PartConfig {
+    /// the MIDI channel it plays from in multi mode, 1 through 16.
+    pub channel: u8,
+    pub engine: PartEngine,
+    /// the bank it starts on, see `midi::bank`.
+    pub bank: u16,
+    /// the program it starts on, 0 through 127.
+    pub program: u8,
+    /// its volume in the mix, 0 to 1.
+    pub level: f32,
+}
+
+impl Default for PartConfig {
+    fn default() -> Self {
+        Self {
+            channel: 1,
+            engine: PartEngine::WaveTable,
+            bank: 0,
+            program: 0,
+            level: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MultiConfig {
+    /// off plays only part 1, on whatever channels the receive filters let through.
+    pub enabled: bool,
+    /// the parts in order, the first is part 1. never more than `MAX_PARTS`.
+    pub parts: Vec<PartConfig>,
+}
+
+impl Default for MultiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            parts: vec![PartConfig::default()],
+        }
+    }
+}
+
+impl MultiConfig {
+    /// what part `index` is built with, `None` if it shouldn't be loaded at all. part 1 always
+    /// is, multi mode or not.
+    pub fn part(&self, index: usize) -> Option<PartConfig> {
+        match index {
+            0 => Some(self.parts.first().copied().unwrap_or_default()),
+            _ if self.enabled => self.parts.get(index).copied(),
+            _ => None,
+        }
+    }
+
+    /// a bit per part that plays `channel` (1 through 16), `None` outside of multi mode.
+    pub fn parts_on(&self, channel: u8) -> Option<u16> {
+        if !self.enabled {
+            return None;
+        }
+
+        let parts = self
+            .parts
+            .iter()
+            .take(MAX_PARTS)
+            .enumerate()
+            .filter(|(_, part)| part.channel == channel)
+            .fold(0, |parts, (i, _)| parts | 1 << i);
+
+        Some(parts)
+    }
+
+    /// true if some part plays the channel `msg` is on. system messages always get through.
+    pub fn covers(&self, msg: &MidiMessage) -> bool {
+        match channel_of(msg) {
+            Some(channel) => self.parts_on(channel).is_some_and(|parts| parts != 0),
+            None => true,
+        }
+    }
+
+    /// adds a part on the lowest channel no other part uses. returns its index, `None` when
+    /// there are already 16.
+    pub fn add_part(&mut self) -> Option<usize> {
+        if self.parts.len() >= MAX_PARTS {
+            return None;
+        }
+
+        let channel = (1..=16)
+            .find(|channel| self.parts.iter().all(|part| part.channel != *channel))
+            .unwrap_or(1);
+
+        self.parts.push(PartConfig {
+            channel,
+            ..PartConfig::default()
+        });
+
+        Some(self.parts.len() - 1)
+    }
+
+    /// removes part `index`, the parts after it move up one. part 1 can't be removed.
+    pub fn remove_part(&mut self, index: usize) {
+        if index > 0 && index < self.parts.len() {
+            self.parts.remove(index);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! keeps the audio device running. the host is shared with the render callback so the device can
+//! be torn down and reopened (new settings, a lost device, a retry) without touching the patch.
+
+use super::SynthHost;
+use crate::audio::latency::{Latency, LatencyMeter};
+use crate::audio::{AudioBackend, AudioError, AudioParams};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// first wait before retrying a failed device, doubled on every failure up to `RETRY_MAX`.
+const RETRY_MIN: Duration = Duration::from_millis(500);
+const RETRY_MAX: Duration = Duration::from_secs(10);
+/// a device that hasn't called back for this long (or eight buffers, if that is longer) is
+/// treated as lost.
+const STALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// how the output is doing, for the UI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioStatus {
+    Running {
+        backend: &'static str,
+        params: AudioParams,
+        /// the device wouldn't take the settings asked for, so `params` are a fallback.
+        fallback: bool,
+    },
+    /// the device failed to start or was lost, another try is coming.
+    Failed { error: String, retry_at: Instant },
+}
+
+#[derive(Debug)]
+pub struct Output {
+    host: Arc<Mutex<SynthHost>>,
+    backend: Box<dyn AudioBackend>,
+    /// what the user asked for. what is really running is in `status`.
+    params: AudioParams,
+    latency: Arc<LatencyMeter>,
+    status: AudioStatus,
+    retry_delay: Duration,
+}
+
+impl Output {
+    /// starts `backend`, falling back to safer params if it has to. never fails: if nothing
+    /// works the output sits in `AudioStatus::Failed` and keeps retrying from `check`.
+    pub fn new(host: SynthHost, backend: Box<dyn AudioBackend>, params: AudioParams) -> Self {
+        let mut output = Self {
+            host: Arc::new(Mutex::new(host)),
+            backend,
+            params,
+            latency: Arc::new(LatencyMeter::default()),
+            status: AudioStatus::Failed {
+                error: "not started".into(),
+                retry_at: Instant::now(),
+            },
+            retry_delay: RETRY_MIN,
+        };
+        output.restart();
+
+        output
+    }
+
+    /// (re)starts the backend with `params`. the host is only locked here while the device is
+    /// stopped, so the audio thread's `try_lock` never actually misses.
+    fn start(&mut self, params: AudioParams) -> Result<(), AudioError> {
+        self.backend.stop();
+
+        if let Ok(mut host) = self.host.lock() {
+            host.prepare(params.buffer_size, params.sample_rate);
+        }
+
+        self.latency.reset();
+
+        let host = self.host.clone();
+        let latency = self.latency.clone();
+        let render = move |data: &mut [f32]| {
+            latency.record(data.len() / params.channels.max(1));
+
+            match host.try_lock() {
+                Ok(mut host) => host.render(data, params.channels),
+                Err(_) => data.fill(0.0),
+            }
+        };
+
+        self.backend.start(params, Box::new(render))?;
+
+        log::info!(
+            "audio started on the {} backend at {} Hz, {} frame buffers, {} channels",
+            self.backend.name(),
+            params.sample_rate,
+            params.buffer_size,
+            params.channels
+        );
+
+        self.status = AudioStatus::Running {
+            backend: self.backend.name(),
+            params,
+            fallback: params != self.params,
+        };
+        self.retry_delay = RETRY_MIN;
+
+        Ok(())
+    }
+
+    /// tries `self.params` and then each fallback, stopping at the first that starts.
+    fn restart(&mut self) {
+        let mut error = None;
+
+        for params in self.params.fallbacks() {
+            match self.start(params) {
+                Ok(()) => return,
+                Err(e) => {
+                    log::warn!("audio would not start with {params:?}: {e}");
+                    error = Some(e);
+                }
+            }
+        }
+
+        if let Some(e) = error {
+            self.fail(e);
+        }
+    }
+
+    /// stops the device and schedules a retry, backing off on repeated failures.
+    fn fail(&mut self, error: AudioError) {
+        log::error!(
+            "audio output failed, retrying in {} ms: {error}",
+            self.retry_delay.as_millis()
+        );
+        self.backend.stop();
+        self.status = AudioStatus::Failed {
+            error: error.to_string(),
+            retry_at: Instant::now() + self.retry_delay,
+        };
+        self.retry_delay = (self.retry_delay * 2).min(RETRY_MAX);
+    }
+
+    /// restarts with new settings, trying only those. if the device won't take them the old
+    /// settings (or their fallbacks) are put back and the error is returned.
+    pub fn set_params(&mut self, params: AudioParams) -> Result<(), AudioError> {
+        if params == self.params && matches!(self.status, AudioStatus::Running { .. }) {
+            return Ok(());
+        }
+
+        let old = std::mem::replace(&mut self.params, params);
+
+        if let Err(e) = self.start(params) {
+            log::error!("could not restart audio with {params:?}: {e}");
+            self.params = old;
+            self.restart();
+
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub fn params(&self) -> AudioParams {
+        self.params
+    }
+
+    pub fn status(&self) -> &AudioStatus {
+        &self.status
+    }
+
+    /// retries a failed device now instead of waiting for the backoff.
+    pub fn retry(&mut self) {
+        self.retry_delay = RETRY_MIN;
+        self.restart();
+    }
+
+    /// notices a lost device and retries failed ones once their backoff is up. called
+    /// regularly by the watchdog.
+    pub fn check(&mut self) {
+        match self.status {
+            AudioStatus::Running { params, .. } => {
+                let timeout = STALL_TIMEOUT.max(params.buffer_duration() * 8);
+                let silent = self.latency.since_callback();
+                let error = self
+                    .backend
+                    .take_error()
+                    .or_else(|| (silent > timeout).then_some(AudioError::Stalled(silent)));
+
+                if let Some(e) = error {
+                    self.fail(e);
+                }
+            }
+            AudioStatus::Failed { retry_at, .. } if Instant::now() >= retry_at => {
+                self.restart();
+            }
+            AudioStatus::Failed { .. } => {}
+        }
+    }
+
+    /// what latency the running settings should give and what the device is really doing.
+    pub fn latency(&self) -> Latency {
+        let AudioStatus::Running { params, .. } = self.status else {
+            return Latency {
+                theoretical: self.params.buffer_duration(),
+                measured: None,
+                callback_period: None,
+            };
+        };
+        let measured = self.latency.frames().map(|frames| {
+            let buffer = Duration::from_secs_f64(frames as f64 / params.sample_rate as f64);
+
+            buffer + self.backend.output_delay().unwrap_or_default()
+        });
+
+        Latency {
+            theoretical: params.buffer_duration(),
+            measured,
+            callback_period: self.latency.period(),
+        }
+    }
+}
@@ -0,0 +1,813 @@
+//! one playable instrument: the engine banks, which keys are down on them, and the per-channel
+//! controller state (pressure, mono mode, ...). owned by the `SynthHost` on the audio thread.
+//! MPE member channels play on engines of their own, see `Member`. the host mixes up to 16 of
+//! these in multi-timbral mode.
+
+use super::bend::{BendRange, ENGINE_BEND_SEMITONES};
+use super::host::SynthCmd;
+use super::stereo::StereoParams;
+use crate::midi::mpe::DEFAULT_MEMBER_BEND;
+use stepper_synth_backend::{
+    pygame_coms::SynthEngineType,
+    synth_engines::{SynthChannel, SynthEngine, SynthModule},
+    KnobCtrl, MidiControlled, SampleGen,
+};
+
+/// output below this is treated as silence when deciding if a bank can go idle.
+const SILENCE: f32 = 1.0e-5;
+/// marks which banks a held key is sounding on, one bit per bank.
+const NO_BANK: u8 = 0;
+/// how much the soft pedal scales the velocity of new notes.
+const SOFT_VELOCITY: f32 = 0.7;
+/// one member engine per MIDI channel.
+const MEMBERS: usize = 16;
+
+/// an MPE member channel's engine. the backend engines bend and swell all their voices at once,
+/// so a channel needs its own engine for its expression to move only its notes.
+struct Member {
+    engine: SynthChannel,
+    /// sounded since it last went quiet, idle members are not rendered.
+    active: bool,
+    /// keys physically down on this channel.
+    down: [bool; 128],
+    /// keys sounding, down or held by the sustain pedal.
+    sounding: [bool; 128],
+    bend: f32,
+    pressure: f32,
+}
+
+impl Member {
+    fn new(engine: SynthEngineType) -> Self {
+        Self {
+            engine: SynthChannel::from(engine),
+            active: false,
+            down: [false; 128],
+            sounding: [false; 128],
+            bend: 0.0,
+            pressure: 0.0,
+        }
+    }
+
+    fn stop(&mut self, key: u8) {
+        self.sounding[key as usize] = false;
+        self.engine.engine.stop(key);
+    }
+}
+
+pub struct Part {
+    /// two copies of the engine, voices are spread across them for stereo.
+    banks: [SynthChannel; 2],
+    /// banks that have sounded since they last went quiet, idle banks are not rendered.
+    active: [bool; 2],
+    /// per key bitmask of the banks it is held on.
+    held: [u8; 128],
+    next_bank: usize,
+    stereo: StereoParams,
+    /// the part's volume in the mix, folded into `gains`.
+    level: f32,
+    gains: [[f32; 2]; 2],
+    /// the three things that drive the engine's volume swell: the mod wheel (or whatever was
+    /// sent as `VolumeSwell`), channel pressure, and per key pressure. the strongest wins.
+    swell: f32,
+    pressure: f32,
+    poly_pressure: [f32; 128],
+    /// the swell last handed to the engines, `None` until something has set it.
+    applied_swell: Option<f32>,
+    /// one note at a time, last note priority.
+    mono: bool,
+    /// keys held in mono mode, most recent last. never grows past 128 so it never reallocates.
+    mono_stack: Vec<u8>,
+    mono_velocity: u8,
+    /// muted after All Sound Off until the release tails die out or a new note starts.
+    hush: bool,
+    /// keys physically down, i.e. note on received and no note off yet. a key can be sounding
+    /// without being down while a pedal holds it.
+    down: [bool; 128],
+    sustain: bool,
+    sostenuto: bool,
+    /// the keys that were down when the sostenuto pedal went down, the only ones it holds.
+    sostenuto_keys: [bool; 128],
+    soft: bool,
+    /// the wheel position, -1 to 1, kept so a new range applies to a wheel that's already bent.
+    bend: f32,
+    bend_range: BendRange,
+    /// MPE member channels, indexed by channel - 1. built with the part, off the audio thread, so
+    /// turning MPE on never allocates there. empty on parts MPE doesn't play.
+    members: Vec<Member>,
+    member_range: BendRange,
+}
+
+impl Part {
+    /// a part without MPE member engines.
+    pub fn new(engine: SynthEngineType) -> Self {
+        let stereo = StereoParams::default();
+
+        Self {
+            banks: [
+                SynthChannel::from(engine.clone()),
+                SynthChannel::from(engine.clone()),
+            ],
+            active: [false; 2],
+            held: [NO_BANK; 128],
+            next_bank: 0,
+            stereo,
+            level: 1.0,
+            gains: stereo.gains(),
+            swell: 0.0,
+            pressure: 0.0,
+            poly_pressure: [0.0; 128],
+            applied_swell: None,
+            mono: false,
+            mono_stack: Vec::with_capacity(128),
+            mono_velocity: 0,
+            hush: false,
+            down: [false; 128],
+            sustain: false,
+            sostenuto: false,
+            sostenuto_keys: [false; 128],
+            soft: false,
+            bend: 0.0,
+            bend_range: BendRange::default(),
+            members: Vec::new(),
+            member_range: BendRange::symmetric(DEFAULT_MEMBER_BEND),
+        }
+    }
+
+    /// a part with an engine for each MPE member channel, for the part MPE zones play on.
+    pub fn with_members(engine: SynthEngineType) -> Self {
+        Self {
+            members: (0..MEMBERS).map(|_| Member::new(engine.clone())).collect(),
+            ..Self::new(engine)
+        }
+    }
+
+    fn update_gains(&mut self) {
+        let level = self.level;
+        self.gains = self
+            .stereo
+            .gains()
+            .map(|gains| gains.map(|gain| gain * level));
+    }
+
+    /// picks the banks a new note sounds on.
+    fn allocate(&mut self, key: u8) -> u8 {
+        match self.held[key as usize] {
+            NO_BANK if self.stereo.unison => 0b11,
+            NO_BANK if self.stereo.is_centered() => 0b01,
+            NO_BANK => {
+                let bank = self.next_bank;
+                self.next_bank ^= 1;
+                1 << bank
+            }
+            // a retrigger stays where the note already is.
+            banks => banks,
+        }
+    }
+
+    /// starts `key` on the engines.
+    fn sound(&mut self, key: u8, velocity: u8) {
+        let banks = self.allocate(key);
+        self.held[key as usize] = banks;
+        self.hush = false;
+
+        for i in 0..2 {
+            if banks & (1 << i) != 0 {
+                self.active[i] = true;
+                self.banks[i].engine.play(key, velocity);
+            }
+        }
+    }
+
+    /// stops `key` on the engines.
+    fn release(&mut self, key: u8) {
+        let banks = std::mem::replace(&mut self.held[key as usize], NO_BANK);
+
+        for i in 0..2 {
+            if banks & (1 << i) != 0 {
+                self.banks[i].engine.stop(key);
+            }
+        }
+
+        if self.poly_pressure[key as usize] != 0.0 {
+            self.poly_pressure[key as usize] = 0.0;
+            self.update_swell();
+        }
+    }
+
+    fn note_on(&mut self, key: u8, velocity: u8) {
+        let velocity = if self.soft {
+            ((velocity as f32 * SOFT_VELOCITY).round() as u8).max(1)
+        } else {
+            velocity
+        };
+
+        self.down[key as usize] = true;
+
+        if self.mono {
+            if let Some(&last) = self.mono_stack.last() {
+                if last != key {
+                    self.release(last);
+                }
+            }
+
+            self.mono_stack.retain(|k| *k != key);
+            self.mono_stack.push(key);
+            self.mono_velocity = velocity;
+        }
+
+        self.sound(key, velocity);
+    }
+
+    /// true if a pedal is keeping `key` sounding after it was let go.
+    fn pedal_holds(&self, key: u8) -> bool {
+        self.sustain || (self.sostenuto && self.sostenuto_keys[key as usize])
+    }
+
+    fn note_off(&mut self, key: u8) {
+        self.down[key as usize] = false;
+
+        // the release is deferred until the pedal lifts.
+        if self.pedal_holds(key) {
+            return;
+        }
+
+        let was_playing = self.mono && self.mono_stack.last() == Some(&key);
+        self.mono_stack.retain(|k| *k != key);
+        self.release(key);
+
+        // in mono mode letting go of the top note falls back to the one held before it.
+        if was_playing {
+            if let Some(&previous) = self.mono_stack.last() {
+                self.sound(previous, self.mono_velocity);
+            }
+        }
+    }
+
+    /// lets go of every key. notes a pedal is holding keep sounding until it lifts.
+    fn all_notes_off(&mut self) {
+        for key in 0..128 {
+            if self.down[key as usize] {
+                self.note_off(key);
+            }
+        }
+
+        for channel in 0..self.members.len() {
+            for key in 0..128 {
+                if self.members[channel].down[key as usize] {
+                    self.member_note_off(channel, key);
+                }
+            }
+        }
+    }
+
+    /// stops every sounding note, pedals or not.
+    fn release_all(&mut self) {
+        self.mono_stack.clear();
+        self.down = [false; 128];
+
+        for key in 0..128 {
+            if self.held[key as usize] != NO_BANK {
+                self.release(key);
+            }
+        }
+
+        for member in self.members.iter_mut() {
+            member.down = [false; 128];
+
+            for key in 0..128 {
+                if member.sounding[key as usize] {
+                    member.stop(key);
+                }
+            }
+        }
+    }
+
+    /// releases the notes that were only sounding because of a pedal that just lifted.
+    fn release_pedalled(&mut self) {
+        for key in 0..128 {
+            let sounding = self.held[key as usize] != NO_BANK;
+
+            if sounding && !self.down[key as usize] && !self.pedal_holds(key) {
+                self.mono_stack.retain(|k| *k != key);
+                self.release(key);
+            }
+        }
+
+        // member channels only follow the sustain pedal, sostenuto is per key on the master.
+        if self.sustain {
+            return;
+        }
+
+        for member in self.members.iter_mut() {
+            for key in 0..128 {
+                if member.sounding[key as usize] && !member.down[key as usize] {
+                    member.stop(key);
+                }
+            }
+        }
+    }
+
+    /// `channel` is 0 through 15 from here down.
+    fn member_note_on(&mut self, channel: usize, key: u8, velocity: u8) {
+        let velocity = if self.soft {
+            ((velocity as f32 * SOFT_VELOCITY).round() as u8).max(1)
+        } else {
+            velocity
+        };
+
+        self.hush = false;
+        self.update_member_bend(channel);
+
+        let member = &mut self.members[channel];
+        member.down[key as usize] = true;
+        member.sounding[key as usize] = true;
+        member.active = true;
+        member.engine.engine.play(key, velocity);
+    }
+
+    fn member_note_off(&mut self, channel: usize, key: u8) {
+        let member = &mut self.members[channel];
+        member.down[key as usize] = false;
+
+        if !self.sustain && member.sounding[key as usize] {
+            member.stop(key);
+        }
+    }
+
+    /// bends a member engine by its own bend plus the master channel's.
+    fn update_member_bend(&mut self, channel: usize) {
+        let master = self.bend_range.semitones(self.bend);
+        let member = &mut self.members[channel];
+        let amount = (self.member_range.semitones(member.bend) + master) / ENGINE_BEND_SEMITONES;
+
+        if amount == 0.0 {
+            member.engine.engine.unbend();
+        } else {
+            member.engine.engine.bend(amount);
+        }
+    }
+
+    fn update_member_swell(&mut self, channel: usize) {
+        let swell = self.applied_swell.unwrap_or(0.0);
+        let member = &mut self.members[channel];
+        member
+            .engine
+            .engine
+            .volume_swell(swell.max(member.pressure));
+    }
+
+    fn set_sustain(&mut self, on: bool) {
+        self.sustain = on;
+
+        if !on {
+            self.release_pedalled();
+        }
+    }
+
+    fn set_sostenuto(&mut self, on: bool) {
+        // pressing it again while it is down doesn't pick up more notes.
+        if on == self.sostenuto {
+            return;
+        }
+
+        self.sostenuto = on;
+
+        if on {
+            self.sostenuto_keys = self.down;
+        } else {
+            self.sostenuto_keys = [false; 128];
+            self.release_pedalled();
+        }
+    }
+
+    /// bends the engines by the wheel position scaled to the range.
+    fn update_bend(&mut self) {
+        let amount = self.bend_range.semitones(self.bend) / ENGINE_BEND_SEMITONES;
+
+        for bank in self.banks.iter_mut() {
+            if amount == 0.0 {
+                bank.engine.unbend();
+            } else {
+                bank.engine.bend(amount);
+            }
+        }
+
+        // the master channel's bend moves every member's notes too.
+        for channel in 0..self.members.len() {
+            if self.members[channel].active {
+                self.update_member_bend(channel);
+            }
+        }
+    }
+
+    /// hands the strongest of swell and pressure to the engines when it changes.
+    fn update_swell(&mut self) {
+        let poly = self
+            .held
+            .iter()
+            .zip(self.poly_pressure.iter())
+            .filter(|(banks, _)| **banks != NO_BANK)
+            .fold(0.0f32, |max, (_, pressure)| max.max(*pressure));
+        let swell = self.swell.max(self.pressure).max(poly);
+
+        if self.applied_swell.is_none() && swell == 0.0 {
+            return;
+        }
+
+        if self.applied_swell != Some(swell) {
+            self.applied_swell = Some(swell);
+
+            for bank in self.banks.iter_mut() {
+                bank.engine.volume_swell(swell);
+            }
+
+            for channel in 0..self.members.len() {
+                if self.members[channel].active {
+                    self.update_member_swell(channel);
+                }
+            }
+        }
+    }
+
+    pub fn apply(&mut self, cmd: SynthCmd) {
+        match cmd {
+            // a note on with no velocity is a note off.
+            SynthCmd::NoteOn { key, velocity: 0 } => self.note_off(key & 0x7f),
+            SynthCmd::NoteOn { key, velocity } => self.note_on(key & 0x7f, velocity),
+            SynthCmd::NoteOff { key } => self.note_off(key & 0x7f),
+            SynthCmd::Stereo(param) => {
+                self.stereo.set(param);
+                self.update_gains();
+            }
+            SynthCmd::Level(level) => {
+                self.level = level.clamp(0.0, 1.0);
+                self.update_gains();
+            }
+            SynthCmd::VolumeSwell(value) => {
+                self.swell = value;
+                self.update_swell();
+            }
+            SynthCmd::Pressure(value) => {
+                self.pressure = value;
+                self.update_swell();
+            }
+            SynthCmd::PolyPressure { key, value } => {
+                let key = key & 0x7f;
+
+                if self.held[key as usize] != NO_BANK {
+                    self.poly_pressure[key as usize] = value;
+                    self.update_swell();
+                }
+            }
+            SynthCmd::Bend(bend) => {
+                self.bend = bend.clamp(-1.0, 1.0);
+                self.update_bend();
+            }
+            // the members' own bend and pressure come and go with their channels' messages.
+            SynthCmd::Unbend => {
+                self.bend = 0.0;
+                self.update_bend();
+            }
+            SynthCmd::BendRange(range) => {
+                self.bend_range = range;
+                self.update_bend();
+            }
+            SynthCmd::MemberNoteOn {
+                channel,
+                key,
+                velocity,
+            } => {
+                let Some(channel) = self.member_index(channel) else {
+                    return;
+                };
+
+                match velocity {
+                    0 => self.member_note_off(channel, key & 0x7f),
+                    velocity => self.member_note_on(channel, key & 0x7f, velocity),
+                }
+            }
+            SynthCmd::MemberNoteOff { channel, key } => {
+                if let Some(channel) = self.member_index(channel) {
+                    self.member_note_off(channel, key & 0x7f);
+                }
+            }
+            SynthCmd::MemberBend { channel, bend } => {
+                if let Some(channel) = self.member_index(channel) {
+                    self.members[channel].bend = bend.clamp(-1.0, 1.0);
+                    self.update_member_bend(channel);
+                }
+            }
+            SynthCmd::MemberPressure { channel, value } => {
+                if let Some(channel) = self.member_index(channel) {
+                    self.members[channel].pressure = value;
+                    self.update_member_swell(channel);
+                }
+            }
+            SynthCmd::MemberKnob {
+                channel,
+                knob,
+                value,
+            } => {
+                if let Some(channel) = self.member_index(channel) {
+                    Self::apply_to(
+                        &mut self.members[channel].engine,
+                        &SynthCmd::Knob { knob, value },
+                    );
+                }
+            }
+            SynthCmd::MemberBendRange(range) => {
+                self.member_range = range;
+
+                for channel in 0..self.members.len() {
+                    self.update_member_bend(channel);
+                }
+            }
+            SynthCmd::Sustain(on) => self.set_sustain(on),
+            SynthCmd::Sostenuto(on) => self.set_sostenuto(on),
+            SynthCmd::Soft(on) => self.soft = on,
+            SynthCmd::AllNotesOff => self.all_notes_off(),
+            SynthCmd::AllSoundOff => {
+                self.release_all();
+                self.hush = true;
+            }
+            SynthCmd::ResetControllers => {
+                self.set_sustain(false);
+                self.set_sostenuto(false);
+                self.soft = false;
+                self.swell = 0.0;
+                self.pressure = 0.0;
+                self.poly_pressure = [0.0; 128];
+                self.update_swell();
+                self.bend = 0.0;
+                self.update_bend();
+            }
+            SynthCmd::Mono(mono) => {
+                // switching modes is an implied All Notes Off.
+                self.release_all();
+                self.mono = mono;
+            }
+            cmd => {
+                for bank in self.banks.iter_mut() {
+                    Self::apply_to(bank, &cmd);
+                }
+
+                for member in self.members.iter_mut() {
+                    Self::apply_to(&mut member.engine, &cmd);
+                }
+            }
+        }
+    }
+
+    /// applies a command that goes to every bank.
+    fn apply_to(synth: &mut SynthChannel, cmd: &SynthCmd) {
+        let engine = &mut synth.engine;
+
+        match *cmd {
+            SynthCmd::Knob { knob, value } => {
+                match knob {
+                    1 => engine.knob_1(value),
+                    2 => engine.knob_2(value),
+                    3 => engine.knob_3(value),
+                    4 => engine.knob_4(value),
+                    5 => engine.knob_5(value),
+                    6 => engine.knob_6(value),
+                    7 => engine.knob_7(value),
+                    8 => engine.knob_8(value),
+                    _ => false,
+                };
+            }
+            SynthCmd::Midi(ref msg) => {
+                if let SynthModule::WaveTable(wt) = engine {
+                    wt.synth.midi_input(msg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// true once nothing is sounding, held or ringing.
+    pub fn is_idle(&self) -> bool {
+        !self.active.contains(&true) && !self.members.iter().any(|member| member.active)
+    }
+
+    /// adds this part's output to `left` and `right`.
+    pub fn render(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (i, bank) in self.banks.iter_mut().enumerate() {
+            if !self.active[i] {
+                continue;
+            }
+
+            let [gain_l, gain_r] = if self.hush { [0.0; 2] } else { self.gains[i] };
+            let mut peak = 0.0f32;
+
+            for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                let sample = bank.get_sample();
+                peak = peak.max(sample.abs());
+                *l += sample * gain_l;
+                *r += sample * gain_r;
+            }
+
+            let holding = self.held.iter().any(|banks| banks & (1 << i) != 0);
+
+            if !holding && peak < SILENCE {
+                self.active[i] = false;
+            }
+        }
+
+        for (i, member) in self.members.iter_mut().enumerate() {
+            if !member.active {
+                continue;
+            }
+
+            // members alternate banks' sides, so spread still spreads.
+            let [gain_l, gain_r] = if self.hush {
+                [0.0; 2]
+            } else {
+                self.gains[i % 2]
+            };
+            let mut peak = 0.0f32;
+
+            for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                let sample = member.engine.get_sample();
+                peak = peak.max(sample.abs());
+                *l += sample * gain_l;
+                *r += sample * gain_r;
+            }
+
+            if !member.sounding.contains(&true) && peak < SILENCE {
+                member.active = false;
+            }
+        }
+
+        let members_active = self.members.iter().any(|member| member.active);
+
+        if self.hush && !self.active.contains(&true) && !members_active {
+            self.hush = false;
+        }
+    }
+
+    /// the index of member channel `channel`, 1 through 16. `None` on parts without members.
+    fn member_index(&self, channel: u8) -> Option<usize> {
+        (1..=self.members.len() as u8)
+            .contains(&channel)
+            .then(|| channel as usize - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part() -> Part {
+        Part::with_members(SynthEngineType::WaveTable)
+    }
+
+    #[test]
+    fn unbend_keeps_member_bend() {
+        let mut part = part();
+        part.apply(SynthCmd::MemberNoteOn {
+            channel: 2,
+            key: 60,
+            velocity: 100,
+        });
+        part.apply(SynthCmd::MemberBend {
+            channel: 2,
+            bend: 0.5,
+        });
+        part.apply(SynthCmd::Bend(0.25));
+        part.apply(SynthCmd::Unbend);
+
+        assert_eq!(part.bend, 0.0);
+        assert_eq!(part.members[1].bend, 0.5);
+        assert!(part.members[1].sounding[60]);
+    }
+
+    fn down(part: &mut Part, key: u8) {
+        part.apply(SynthCmd::NoteOn { key, velocity: 100 });
+    }
+
+    fn up(part: &mut Part, key: u8) {
+        part.apply(SynthCmd::NoteOff { key });
+    }
+
+    fn sounding(part: &Part, key: u8) -> bool {
+        part.held[key as usize] != NO_BANK
+    }
+
+    #[test]
+    fn sustain_releases_only_keys_that_are_up() {
+        let mut part = part();
+        down(&mut part, 60);
+        down(&mut part, 64);
+        part.apply(SynthCmd::Sustain(true));
+        up(&mut part, 60);
+        down(&mut part, 67);
+
+        assert!(sounding(&part, 60));
+        assert!(sounding(&part, 64));
+        assert!(sounding(&part, 67));
+
+        part.apply(SynthCmd::Sustain(false));
+
+        assert!(!sounding(&part, 60));
+        assert!(sounding(&part, 64));
+        assert!(sounding(&part, 67));
+
+        up(&mut part, 64);
+        assert!(!sounding(&part, 64));
+    }
+
+    #[test]
+    fn sostenuto_holds_only_keys_down_at_press() {
+        let mut part = part();
+        down(&mut part, 48);
+        part.apply(SynthCmd::Sostenuto(true));
+        down(&mut part, 60);
+
+        up(&mut part, 48);
+        up(&mut part, 60);
+        assert!(sounding(&part, 48));
+        assert!(!sounding(&part, 60));
+
+        // pressing it again while it is down doesn't pick up the keys held now.
+        down(&mut part, 62);
+        part.apply(SynthCmd::Sostenuto(true));
+        up(&mut part, 62);
+        assert!(!sounding(&part, 62));
+
+        part.apply(SynthCmd::Sostenuto(false));
+        assert!(!sounding(&part, 48));
+    }
+
+    #[test]
+    fn sostenuto_and_sustain() {
+        let mut part = part();
+        down(&mut part, 48);
+        part.apply(SynthCmd::Sostenuto(true));
+        part.apply(SynthCmd::Sustain(true));
+        up(&mut part, 48);
+        down(&mut part, 60);
+        up(&mut part, 60);
+
+        // lifting sustain leaves what sostenuto holds.
+        part.apply(SynthCmd::Sustain(false));
+        assert!(sounding(&part, 48));
+        assert!(!sounding(&part, 60));
+
+        part.apply(SynthCmd::Sostenuto(false));
+        assert!(!sounding(&part, 48));
+    }
+
+    #[test]
+    fn restrike_sustained_key() {
+        let mut part = part();
+        part.apply(SynthCmd::Sustain(true));
+        down(&mut part, 60);
+        up(&mut part, 60);
+        let banks = part.held[60];
+
+        // struck again while the pedal holds it, on the same banks.
+        down(&mut part, 60);
+        assert!(part.down[60]);
+        assert_eq!(part.held[60], banks);
+
+        // still down when the pedal lifts, so it keeps sounding.
+        part.apply(SynthCmd::Sustain(false));
+        assert!(sounding(&part, 60));
+
+        up(&mut part, 60);
+        assert!(!sounding(&part, 60));
+    }
+
+    #[test]
+    fn member_sustain() {
+        let mut part = part();
+        part.apply(SynthCmd::Sustain(true));
+        part.apply(SynthCmd::MemberNoteOn {
+            channel: 3,
+            key: 60,
+            velocity: 100,
+        });
+        part.apply(SynthCmd::MemberNoteOff {
+            channel: 3,
+            key: 60,
+        });
+        assert!(part.members[2].sounding[60]);
+
+        part.apply(SynthCmd::Sustain(false));
+        assert!(!part.members[2].sounding[60]);
+    }
+
+    #[test]
+    fn members_only_with_members() {
+        let mut part = Part::new(SynthEngineType::WaveTable);
+        part.apply(SynthCmd::MemberNoteOn {
+            channel: 2,
+            key: 60,
+            velocity: 100,
+        });
+
+        assert!(part.members.is_empty());
+        assert!(part.is_idle());
+    }
+}
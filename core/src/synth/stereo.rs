@@ -0,0 +1,93 @@
+//! stereo placement. the engines are mono, so voices are spread by splitting them across two
+//! engine banks panned away from each other; width and master pan then act on that image.
+
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, SQRT_2};
+
+/// MIDI CCs for the stereo controls. 10 is the standard pan CC.
+pub const PAN_CC: u8 = 10;
+pub const WIDTH_CC: u8 = 78;
+pub const SPREAD_CC: u8 = 79;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StereoParams {
+    /// master balance, -1 (left) to 1 (right).
+    pub pan: f32,
+    /// 0 is mono, 1 leaves the image alone, 2 exaggerates it.
+    pub width: f32,
+    /// how far apart the two voice banks sit, 0 (both centered) to 1 (hard left/right).
+    pub spread: f32,
+    /// every note plays on both banks instead of alternating between them.
+    pub unison: bool,
+}
+
+impl Default for StereoParams {
+    fn default() -> Self {
+        Self {
+            pan: 0.0,
+            width: 1.0,
+            spread: 0.0,
+            unison: false,
+        }
+    }
+}
+
+/// a change to one stereo control, sent from the UI or the MIDI router.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoParam {
+    Pan(f32),
+    Width(f32),
+    Spread(f32),
+    Unison(bool),
+}
+
+impl StereoParams {
+    pub fn set(&mut self, param: StereoParam) {
+        match param {
+            StereoParam::Pan(pan) => self.pan = pan.clamp(-1.0, 1.0),
+            StereoParam::Width(width) => self.width = width.clamp(0.0, 2.0),
+            StereoParam::Spread(spread) => self.spread = spread.clamp(0.0, 1.0),
+            StereoParam::Unison(unison) => self.unison = unison,
+        }
+    }
+
+    /// true when every voice ends up in the center, so a single bank is enough.
+    pub fn is_centered(&self) -> bool {
+        self.spread == 0.0 && !self.unison
+    }
+
+    /// `[left, right]` gain of each bank, with spread, width, and pan folded together.
+    pub fn gains(&self) -> [[f32; 2]; 2] {
+        // constant power pan, scaled so the center is unity gain.
+        let place = |pos: f32| {
+            let angle = (pos + 1.0) * FRAC_PI_4;
+            [SQRT_2 * angle.cos(), SQRT_2 * angle.sin()]
+        };
+        // balance only ever turns a side down so a hard pan can't clip.
+        let balance = [
+            if self.pan > 0.0 {
+                (self.pan * FRAC_PI_2).cos()
+            } else {
+                1.0
+            },
+            if self.pan < 0.0 {
+                (-self.pan * FRAC_PI_2).cos()
+            } else {
+                1.0
+            },
+        ];
+        // two banks playing the same notes sum roughly as uncorrelated signals.
+        let level = if self.unison { 1.0 / SQRT_2 } else { 1.0 };
+
+        [place(-self.spread), place(self.spread)].map(|[l, r]| {
+            let wide = (1.0 + self.width) * 0.5;
+            let narrow = (1.0 - self.width) * 0.5;
+
+            [
+                (l * wide + r * narrow) * balance[0] * level,
+                (l * narrow + r * wide) * balance[1] * level,
+            ]
+        })
+    }
+}
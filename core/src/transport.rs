@@ -0,0 +1,439 @@
+//! the app's tempo and song position. the transport either follows MIDI clock from a controller
+//! or runs a clock of its own, set by hand or by tapping, which can also be sent out as MIDI
+//! clock. anything that syncs to tempo subscribes to it, see `subscribe`.
+
+use crate::midi::{output, SystemMessage};
+use crossbeam::channel::{bounded, Receiver, Sender, TrySendError};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// MIDI clocks to a quarter note.
+pub const PPQN: u64 = 24;
+/// a song position pointer counts sixteenth notes.
+const CLOCKS_PER_SIXTEENTH: u64 = PPQN / 4;
+const BEATS_PER_BAR: u64 = 4;
+
+pub const MIN_BPM: f64 = 20.0;
+pub const MAX_BPM: f64 = 300.0;
+
+/// how far each new clock interval moves the followed tempo. lower is steadier but slower to
+/// catch up with a tempo change.
+const SMOOTHING: f64 = 0.1;
+/// incoming clock that stops for this long isn't being followed anymore.
+const CLOCK_TIMEOUT: Duration = Duration::from_millis(500);
+/// taps further apart than this start counting again.
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// the taps averaged for tap tempo.
+const MAX_TAPS: usize = 8;
+/// how often the clock thread looks again while the clock is external.
+const IDLE_POLL: Duration = Duration::from_millis(10);
+/// events a subscriber can fall behind by before it misses some.
+const SUBSCRIBER_QUEUE: usize = 64;
+
+lazy_static! {
+    static ref TRANSPORT: Mutex<Transport> = Mutex::new(Transport::default());
+}
+
+/// where tempo comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClockSource {
+    /// our own clock, at the set tempo.
+    #[default]
+    Internal,
+    /// MIDI clock from any enabled input.
+    Midi,
+}
+
+impl ClockSource {
+    pub const ALL: [ClockSource; 2] = [ClockSource::Internal, ClockSource::Midi];
+}
+
+impl fmt::Display for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Internal => write!(f, "Internal"),
+            Self::Midi => write!(f, "MIDI clock"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportConfig {
+    pub source: ClockSource,
+    /// the internal clock's tempo.
+    pub bpm: f64,
+    /// send the internal clock, start, and stop to the MIDI outputs.
+    pub send_clock: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            source: ClockSource::Internal,
+            bpm: 120.0,
+            send_clock: false,
+        }
+    }
+}
+
+/// what subscribers hear about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransportEvent {
+    /// the tempo changed, in BPM.
+    Tempo(f64),
+    Started,
+    Stopped,
+    /// a quarter note went by while playing.
+    Beat(Position),
+}
+
+/// where the song is, in 4/4. bars and beats count from 1, ticks are MIDI clocks into the beat.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub bar: u64,
+    pub beat: u64,
+    pub tick: u64,
+}
+
+impl Position {
+    pub fn from_clocks(clocks: u64) -> Self {
+        let beats = clocks / PPQN;
+
+        Self {
+            bar: beats / BEATS_PER_BAR + 1,
+            beat: beats % BEATS_PER_BAR + 1,
+            tick: clocks % PPQN,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{:02}", self.bar, self.beat, self.tick)
+    }
+}
+
+#[derive(Debug)]
+struct Transport {
+    config: TransportConfig,
+    playing: bool,
+    /// MIDI clocks since the start of the song.
+    clocks: u64,
+    /// the tempo in use, the set one or the one being followed.
+    bpm: f64,
+    /// when the last incoming clock arrived.
+    last_clock: Option<Instant>,
+    /// smoothed seconds between incoming clocks.
+    clock_period: Option<f64>,
+    taps: VecDeque<Instant>,
+    subscribers: Vec<Sender<TransportEvent>>,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        let config = TransportConfig::default();
+
+        Self {
+            config,
+            playing: false,
+            clocks: 0,
+            bpm: config.bpm,
+            last_clock: None,
+            clock_period: None,
+            taps: VecDeque::with_capacity(MAX_TAPS),
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl Transport {
+    /// tells every subscriber, dropping the ones that went away. a subscriber that is full just
+    /// misses this one.
+    fn notify(&mut self, event: TransportEvent) {
+        self.subscribers
+            .retain(|subscriber| match subscriber.try_send(event) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    fn set_bpm(&mut self, bpm: f64) {
+        let bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+
+        if (bpm - self.bpm).abs() >= 0.01 {
+            self.bpm = bpm;
+            self.notify(TransportEvent::Tempo(bpm));
+        }
+    }
+
+    /// one MIDI clock went by, from either clock.
+    fn clock(&mut self) {
+        if !self.playing {
+            return;
+        }
+
+        self.clocks += 1;
+
+        if self.clocks % PPQN == 0 {
+            self.notify(TransportEvent::Beat(Position::from_clocks(self.clocks)));
+        }
+    }
+
+    fn start(&mut self, from_top: bool) {
+        if from_top {
+            self.clocks = 0;
+        }
+
+        self.playing = true;
+        self.notify(TransportEvent::Started);
+        self.notify(TransportEvent::Beat(Position::from_clocks(self.clocks)));
+    }
+
+    fn stop(&mut self) {
+        if self.playing {
+            self.playing = false;
+            self.notify(TransportEvent::Stopped);
+        }
+    }
+
+    /// folds the time since the last incoming clock into the followed tempo. gaps too long or
+    /// short to be a clock in the tempo range are skipped, like the first clock after a pause.
+    fn follow(&mut self, time: Instant) {
+        let last = self.last_clock.replace(time);
+        let Some(interval) = last.and_then(|last| time.checked_duration_since(last)) else {
+            return;
+        };
+
+        let interval = interval.as_secs_f64();
+        let fastest = 60.0 / (MAX_BPM * PPQN as f64);
+        let slowest = 60.0 / (MIN_BPM * PPQN as f64);
+
+        if !(fastest..=slowest).contains(&interval) {
+            return;
+        }
+
+        let period = match self.clock_period {
+            Some(period) => period + (interval - period) * SMOOTHING,
+            None => interval,
+        };
+
+        self.clock_period = Some(period);
+        self.set_bpm(60.0 / (period * PPQN as f64));
+    }
+
+    fn following(&self) -> bool {
+        self.config.source == ClockSource::Midi
+            && self
+                .last_clock
+                .is_some_and(|last| last.elapsed() < CLOCK_TIMEOUT)
+    }
+}
+
+/// sends `msg` to the MIDI outputs if the internal clock is being sent.
+fn send_out(transport: &Transport, msg: SystemMessage) {
+    if transport.config.source == ClockSource::Internal && transport.config.send_clock {
+        output::send(msg.encode());
+    }
+}
+
+/// applies saved or changed settings.
+pub fn configure(config: TransportConfig) {
+    let Ok(mut transport) = TRANSPORT.lock() else {
+        return;
+    };
+
+    if transport.config.source != config.source {
+        transport.stop();
+        transport.last_clock = None;
+        transport.clock_period = None;
+    }
+
+    transport.config = config;
+
+    if config.source == ClockSource::Internal {
+        transport.set_bpm(config.bpm);
+    }
+}
+
+/// a clock or transport message from a MIDI input, which only counts when following MIDI clock.
+pub fn receive(msg: SystemMessage, time: Instant) {
+    let Ok(mut transport) = TRANSPORT.lock() else {
+        return;
+    };
+
+    if transport.config.source != ClockSource::Midi {
+        return;
+    }
+
+    match msg {
+        SystemMessage::Clock => {
+            transport.follow(time);
+            transport.clock();
+        }
+        SystemMessage::Start => transport.start(true),
+        SystemMessage::Continue => transport.start(false),
+        SystemMessage::Stop => transport.stop(),
+        SystemMessage::SongPosition(sixteenths) => {
+            transport.clocks = sixteenths as u64 * CLOCKS_PER_SIXTEENTH;
+        }
+    }
+}
+
+/// starts the internal clock from the top, or from where it stopped with `from_top` false.
+/// does nothing while following MIDI clock.
+pub fn start(from_top: bool) {
+    let Ok(mut transport) = TRANSPORT.lock() else {
+        return;
+    };
+
+    if transport.config.source == ClockSource::Internal {
+        transport.start(from_top);
+        let msg = match from_top {
+            true => SystemMessage::Start,
+            false => SystemMessage::Continue,
+        };
+        send_out(&transport, msg);
+    }
+}
+
+pub fn stop() {
+    let Ok(mut transport) = TRANSPORT.lock() else {
+        return;
+    };
+
+    if transport.config.source == ClockSource::Internal {
+        transport.stop();
+        send_out(&transport, SystemMessage::Stop);
+    }
+}
+
+/// a tap of the tap tempo button at `time`. returns the tapped tempo once there are two taps
+/// close enough together, which the internal clock then runs at.
+pub fn tap(time: Instant) -> Option<f64> {
+    let mut transport = TRANSPORT.lock().ok()?;
+
+    if transport
+        .taps
+        .back()
+        .is_some_and(|last| time.saturating_duration_since(*last) > TAP_TIMEOUT)
+    {
+        transport.taps.clear();
+    }
+
+    if transport.taps.len() == MAX_TAPS {
+        transport.taps.pop_front();
+    }
+
+    transport.taps.push_back(time);
+
+    let first = *transport.taps.front()?;
+    let intervals = transport.taps.len() as f64 - 1.0;
+
+    if intervals < 1.0 {
+        return None;
+    }
+
+    let beat = time.saturating_duration_since(first).as_secs_f64() / intervals;
+    let bpm = (60.0 / beat).clamp(MIN_BPM, MAX_BPM);
+
+    transport.config.bpm = bpm;
+
+    if transport.config.source == ClockSource::Internal {
+        transport.set_bpm(bpm);
+    }
+
+    Some(bpm)
+}
+
+pub fn bpm() -> f64 {
+    TRANSPORT
+        .lock()
+        .map(|transport| transport.bpm)
+        .unwrap_or(TransportConfig::default().bpm)
+}
+
+pub fn position() -> Position {
+    TRANSPORT
+        .lock()
+        .map(|transport| Position::from_clocks(transport.clocks))
+        .unwrap_or_default()
+}
+
+pub fn playing() -> bool {
+    TRANSPORT
+        .lock()
+        .map(|transport| transport.playing)
+        .unwrap_or(false)
+}
+
+/// true while MIDI clock is the source and is arriving.
+pub fn following() -> bool {
+    TRANSPORT
+        .lock()
+        .map(|transport| transport.following())
+        .unwrap_or(false)
+}
+
+/// a feed of tempo changes, starts, stops, and beats. the current tempo comes first.
+pub fn subscribe() -> Receiver<TransportEvent> {
+    let (send, recv) = bounded(SUBSCRIBER_QUEUE);
+
+    if let Ok(mut transport) = TRANSPORT.lock() {
+        let _ = send.try_send(TransportEvent::Tempo(transport.bpm));
+        transport.subscribers.push(send);
+    }
+
+    recv
+}
+
+/// spawns the thread that runs the internal clock. clocks are scheduled against absolute
+/// deadlines so sleep overshoot doesn't add up into drift.
+pub fn spawn_clock() -> JoinHandle<()> {
+    std::thread::Builder::new()
+        .name("transport".into())
+        .spawn(|| {
+            let mut next: Option<Instant> = None;
+
+            loop {
+                let wait = {
+                    let Ok(mut transport) = TRANSPORT.lock() else {
+                        return;
+                    };
+
+                    if transport.config.source != ClockSource::Internal {
+                        next = None;
+                        IDLE_POLL
+                    } else {
+                        let period = Duration::from_secs_f64(60.0 / (transport.bpm * PPQN as f64));
+                        let now = Instant::now();
+                        let due = *next.get_or_insert(now);
+
+                        if now >= due {
+                            transport.clock();
+                            send_out(&transport, SystemMessage::Clock);
+
+                            // after a stall, pick up from now instead of racing to catch up.
+                            let following = due + period;
+                            next = Some(if following < now {
+                                now + period
+                            } else {
+                                following
+                            });
+                        }
+
+                        next.map_or(IDLE_POLL, |next| next.saturating_duration_since(now))
+                    }
+                };
+
+                sleep(wait);
+            }
+        })
+        .expect("could not spawn transport thread")
+}
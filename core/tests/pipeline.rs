@@ -0,0 +1,119 @@
+//! the pieces that run without hardware: `MemorySource` feeding the router and a headless synth,
+//! and `NullBackend` pulling audio.
+
+use crossbeam::channel::unbounded;
+use midi_control::MidiMessage;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use synth_tab_core::audio::null::NullBackend;
+use synth_tab_core::audio::{AudioBackend, AudioParams};
+use synth_tab_core::midi::source::{spawn_source, MemorySource};
+use synth_tab_core::midi::spawn_router;
+use synth_tab_core::synth::TabSynth;
+use synth_tab_core::MIDI_SEND;
+
+fn chord() -> Vec<MidiMessage> {
+    [60, 64, 67]
+        .into_iter()
+        .map(|key| MidiMessage::from([0x90, key, 100].as_slice()))
+        .collect()
+}
+
+#[test]
+fn memory_source_sends_in_order() {
+    let (tx, rx) = unbounded();
+    let messages = chord();
+    let source = spawn_source(
+        Box::new(MemorySource {
+            messages: messages.clone(),
+        }),
+        tx,
+    );
+
+    let events: Vec<_> = rx.iter().take(messages.len()).collect();
+
+    assert_eq!(source.name, "memory");
+    assert!(events.iter().all(|event| &*event.source == "memory"));
+    assert_eq!(
+        events
+            .iter()
+            .map(|event| format!("{:?}", event.msg))
+            .collect::<Vec<_>>(),
+        messages
+            .iter()
+            .map(|msg| format!("{msg:?}"))
+            .collect::<Vec<_>>()
+    );
+
+    // it finishes once the list runs out, which drops its end of the channel.
+    drop(source);
+    assert!(rx.recv_timeout(Duration::from_secs(1)).is_err());
+}
+
+#[test]
+fn memory_source_plays_headless_synth() {
+    let (tab_synth, mut host) = TabSynth::headless();
+    let tab_synth = Arc::new(RwLock::new(tab_synth));
+    spawn_router(tab_synth.clone());
+
+    let _source = spawn_source(
+        Box::new(MemorySource { messages: chord() }),
+        MIDI_SEND.clone(),
+    );
+
+    let mut left = vec![0.0; 256];
+    let mut right = vec![0.0; 256];
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let mut peak = 0.0f32;
+
+    while peak < 0.001 && Instant::now() < deadline {
+        host.render_block(&mut left, &mut right);
+        peak = left
+            .iter()
+            .chain(&right)
+            .fold(peak, |peak, s| peak.max(s.abs()));
+        sleep(Duration::from_millis(1));
+    }
+
+    assert!(peak >= 0.001, "the chord never sounded, peak {peak}");
+}
+
+#[test]
+fn null_backend_pulls_until_stopped() {
+    let params = AudioParams {
+        sample_rate: 48_000,
+        channels: 2,
+        buffer_size: 128,
+    };
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let mut backend = NullBackend::default();
+
+    backend
+        .start(
+            params,
+            Box::new({
+                let pulled = pulled.clone();
+
+                move |buf: &mut [f32]| {
+                    assert_eq!(buf.len(), 128 * 2);
+                    pulled.fetch_add(1, Ordering::Relaxed);
+                }
+            }),
+        )
+        .unwrap();
+
+    sleep(Duration::from_millis(100));
+    backend.stop();
+
+    // 100 ms is about 37 buffers of 128 frames, give or take the scheduler.
+    let after_stop = pulled.load(Ordering::Relaxed);
+    assert!(
+        (5..=60).contains(&after_stop),
+        "pulled {after_stop} buffers"
+    );
+
+    sleep(Duration::from_millis(20));
+    assert_eq!(pulled.load(Ordering::Relaxed), after_stop);
+}
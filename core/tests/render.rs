@@ -0,0 +1,56 @@
+//! renders the MIDI files in `tests/data` and checks what comes out.
+
+use std::path::{Path, PathBuf};
+use stepper_synth_backend::SAMPLE_RATE;
+use synth_tab_core::render::{render_midi_file, RenderOptions};
+
+fn data(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/data")
+        .join(name)
+}
+
+fn out(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+/// a C major triad held for a beat at 120 bpm.
+#[test]
+fn renders_chord() {
+    let wav = out("chord.wav");
+    let opts = RenderOptions::default();
+    let frames = render_midi_file(&data("chord.mid"), &wav, opts).unwrap();
+
+    let rate = SAMPLE_RATE as f64;
+    let expected = (0.5 * rate).round() as u64 + (opts.tail * rate).round() as u64;
+    assert_eq!(frames, expected);
+
+    let reader = hound::WavReader::open(&wav).unwrap();
+    let spec = reader.spec();
+    assert_eq!(spec.channels, 2);
+    assert_eq!(spec.sample_rate, SAMPLE_RATE as u32);
+    assert_eq!(reader.duration() as u64, frames);
+
+    let samples: Vec<f32> = reader.into_samples().map(Result::unwrap).collect();
+    assert_eq!(samples.len() as u64, frames * 2);
+
+    // the chord sounds while it is held.
+    let held = &samples[..(0.5 * rate) as usize * 2];
+    let peak = held.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    assert!(peak > 0.001, "chord rendered silent, peak {peak}");
+    assert!(samples.iter().all(|s| s.is_finite()));
+}
+
+/// the tail sets how much is rendered after the last event.
+#[test]
+fn shorter_tail() {
+    let wav = out("tail.wav");
+    let opts = RenderOptions { tail: 0.25 };
+    let frames = render_midi_file(&data("chord.mid"), &wav, opts).unwrap();
+
+    let rate = SAMPLE_RATE as f64;
+    assert_eq!(
+        frames,
+        (0.5 * rate).round() as u64 + (0.25 * rate).round() as u64
+    );
+}
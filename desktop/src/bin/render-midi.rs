@@ -0,0 +1,35 @@
+// renders a Standard MIDI File to a WAV file through the synth, without an audio device.
+//
+// usage: render-midi <in.mid> <out.wav> [tail seconds]
+
+use std::path::PathBuf;
+use synth_tab_core::render::{render_midi_file, RenderOptions};
+
+fn main() {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let (Some(midi), Some(wav)) = (args.next(), args.next()) else {
+        eprintln!("usage: render-midi <in.mid> <out.wav> [tail seconds]");
+        std::process::exit(2);
+    };
+    let mut opts = RenderOptions::default();
+
+    if let Some(tail) = args.next() {
+        match tail.parse() {
+            Ok(tail) => opts.tail = tail,
+            Err(e) => {
+                eprintln!("invalid tail length {tail:?}: {e}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    match render_midi_file(&PathBuf::from(&midi), &PathBuf::from(&wav), opts) {
+        Ok(frames) => println!("wrote {frames} frames to {wav}"),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+/// in process clipboard used in place of the android clipboard bridge.
+#[derive(Default)]
+pub struct Clipboard {
+    contents: Option<String>,
+}
+
+impl iced_core::Clipboard for Clipboard {
+    fn read(&self, _kind: iced_core::clipboard::Kind) -> Option<String> {
+        log::debug!("Clipboard read method called");
+        self.contents.clone()
+    }
+    fn write(&mut self, _kind: iced_core::clipboard::Kind, contents: String) {
+        log::debug!("Clipboard write method called");
+        self.contents = Some(contents);
+    }
+}
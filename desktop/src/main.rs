@@ -0,0 +1,189 @@
+//! desktop (linux) build of the app. lets the UI, synth, and MIDI router run in a plain winit
+//! window on a workstation or in CI.
+
+use log::LevelFilter;
+use std::path::PathBuf;
+use synth_tab_core::audio::BackendConfig;
+use synth_tab_core::midi::alsa::{AlsaRawOutput, AlsaRawSource, AlsaSeqSource};
+use synth_tab_core::midi::output::MidiOutput;
+use synth_tab_core::midi::source::{MidiSource, ReplaySource};
+use synth_tab_core::winit::event_loop::EventLoop;
+use synth_tab_core::{Platform, UserEvent};
+
+mod clipboard;
+
+/// there is no JVM on desktop and the physical keyboard is always there, so show/hide keyboard
+/// have nothing to do.
+struct DesktopPlatform {
+    audio: BackendConfig,
+    midi: Vec<String>,
+    midi_out: Vec<String>,
+    data_dir: Option<PathBuf>,
+}
+
+impl Platform for DesktopPlatform {
+    type Clipboard = clipboard::Clipboard;
+
+    fn clipboard(&self) -> Self::Clipboard {
+        clipboard::Clipboard::default()
+    }
+
+    fn show_keyboard(&self) {
+        log::debug!("no soft keyboard on desktop");
+    }
+
+    fn hide_keyboard(&self) {
+        log::debug!("no soft keyboard on desktop");
+    }
+
+    fn audio_backend(&self) -> BackendConfig {
+        self.audio.clone()
+    }
+
+    fn data_dir(&self) -> Option<PathBuf> {
+        self.data_dir.clone()
+    }
+
+    fn midi_sources(&self) -> Vec<Box<dyn MidiSource>> {
+        self.midi
+            .iter()
+            .filter_map(|spec| midi_source(spec))
+            .collect()
+    }
+
+    fn midi_outputs(&self) -> Vec<Box<dyn MidiOutput>> {
+        self.midi_out
+            .iter()
+            .filter_map(|spec| midi_output(spec))
+            .collect()
+    }
+}
+
+/// parses a `--midi` value: `alsa-seq`, `alsa-raw:<device>`, `file:<path>` or
+/// `file-loop:<path>`.
+fn midi_source(spec: &str) -> Option<Box<dyn MidiSource>> {
+    let (kind, arg) = spec.split_once(':').unwrap_or((spec, ""));
+
+    match (kind, arg) {
+        ("alsa-seq", "") => Some(Box::new(AlsaSeqSource::default())),
+        ("alsa-seq", port) => Some(Box::new(AlsaSeqSource { port: port.into() })),
+        ("alsa-raw", device) if !device.is_empty() => Some(Box::new(AlsaRawSource {
+            device: device.into(),
+        })),
+        ("file" | "file-loop", path) if !path.is_empty() => Some(Box::new(ReplaySource {
+            path: PathBuf::from(path),
+            looping: kind == "file-loop",
+        })),
+        _ => {
+            log::error!("unknown MIDI source {spec:?}");
+            None
+        }
+    }
+}
+
+/// parses a `--midi-out` value, only `alsa-raw:<device>` for now.
+fn midi_output(spec: &str) -> Option<Box<dyn MidiOutput>> {
+    match spec.split_once(':') {
+        Some(("alsa-raw", device)) if !device.is_empty() => {
+            Some(Box::new(AlsaRawOutput::new(device.into())))
+        }
+        _ => {
+            log::error!("unknown MIDI output {spec:?}");
+            None
+        }
+    }
+}
+
+/// every `--midi-out <output>` argument. there are none by default.
+fn midi_out_specs() -> Vec<String> {
+    let mut args = std::env::args().skip(1);
+    let mut specs = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--midi-out" {
+            specs.extend(args.next());
+        }
+    }
+
+    specs
+}
+
+/// every `--midi <source>` argument, defaults to an ALSA sequencer port when none are given.
+fn midi_specs() -> Vec<String> {
+    let mut args = std::env::args().skip(1);
+    let mut specs = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--midi" {
+            specs.extend(args.next());
+        }
+    }
+
+    if specs.is_empty() {
+        specs.push("alsa-seq".into());
+    }
+
+    specs
+}
+
+/// picks the audio backend from `--audio <backend>` or `SYNTH_TAB_AUDIO`, falling back to cpal.
+/// see `BackendConfig::from_str` for the accepted values.
+fn audio_backend() -> BackendConfig {
+    let mut args = std::env::args().skip(1);
+    let spec = loop {
+        match args.next().as_deref() {
+            Some("--audio") => break args.next(),
+            Some(_) => continue,
+            None => break std::env::var("SYNTH_TAB_AUDIO").ok(),
+        }
+    };
+
+    match spec.map(|spec| spec.parse()) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            log::error!("{e}, using cpal");
+            BackendConfig::Cpal
+        }
+        None => BackendConfig::Cpal,
+    }
+}
+
+/// where settings are kept: `--config-dir <dir>`, or `synth-tab` under the XDG config directory.
+fn data_dir() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--config-dir" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("synth-tab"))
+}
+
+fn main() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Info)
+        .parse_default_env()
+        .init();
+
+    log::info!("desktop main started");
+
+    let event_loop = EventLoop::<UserEvent>::with_user_event()
+        .build()
+        .expect("Should build event loop");
+
+    log::info!("eventloop made");
+
+    let platform = DesktopPlatform {
+        audio: audio_backend(),
+        midi: midi_specs(),
+        midi_out: midi_out_specs(),
+        data_dir: data_dir(),
+    };
+
+    synth_tab_core::run(event_loop, platform);
+}
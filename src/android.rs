@@ -1,53 +1,99 @@
 extern crate jni;
 
-use super::*;
+use crossbeam::channel::{unbounded, Receiver, Sender};
 use jni::objects::{JByteArray, JClass, JList, JString, ReleaseMode};
+use jni::sys::{jint, jlong};
 use jni::JNIEnv;
-use midi_control::MidiMessage;
-use stepper_synth_backend::MidiControlled;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use synth_tab_core::midi;
+use synth_tab_core::midi::devices::{self, DeviceId, MidiDevice};
+use synth_tab_core::midi::output::MidiOutput;
+use synth_tab_core::midi::parser::MidiParser;
+use synth_tab_core::midi::source::{ChannelSource, MidiSink, MidiSource, Pushed};
+
+lazy_static! {
+    /// messages pushed in by `sendMidiMessage`, drained by `JniSource`.
+    static ref JNI_MIDI: (Sender<Pushed>, Receiver<Pushed>) = unbounded();
+    /// the bytes from `sendMidiMessage` are a stream per device, not one message per call, so
+    /// each device's parse state has to outlive each call.
+    static ref JNI_PARSERS: Mutex<HashMap<DeviceId, MidiParser>> = Mutex::new(HashMap::new());
+}
+
+/// MIDI from the devices `AppMidiManager` has opened on the java side, each message tagged with
+/// the device it came from.
+pub struct JniSource;
+
+impl MidiSource for JniSource {
+    fn name(&self) -> String {
+        "android".into()
+    }
+
+    fn run(self: Box<Self>, sink: MidiSink, stop: Arc<AtomicBool>) {
+        Box::new(ChannelSource::new(self.name(), JNI_MIDI.1.clone())).run(sink, stop)
+    }
+}
+
+/// MIDI out to the device `AppMidiManager` opened for sending.
+pub struct JniOutput(crate::java::MidiWriter);
+
+impl JniOutput {
+    /// `None` if the activity can't be reached.
+    pub fn new() -> Option<Self> {
+        crate::java::MidiWriter::new().map(Self)
+    }
+}
+
+impl MidiOutput for JniOutput {
+    fn name(&self) -> String {
+        "android".into()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+}
 
 #[no_mangle]
-pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_newMidiDev(
+pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_midiDeviceAdded(
     mut env: JNIEnv,
     _: JClass,
-    newMidiDev: JString,
+    device_id: jint,
+    manufacturer: JString,
+    name: JString,
+    inputs: jint,
+    outputs: jint,
 ) {
-    // // Our Java companion code might pass-in "world" as a string, hence the name.
-    // let world = rust_greeting(env.get_string(java_pattern).expect("invalid pattern string").as_ptr());
-    // // Retake pointer so that we can use it below and allow memory to be freed when it goes out of scope.
-    // let world_ptr = CString::from_raw(world);
-    // let output = env.new_string(world_ptr.to_str().unwrap()).expect("Couldn't create java string!");
-
-    // output.into_inner()
-
-    // let world = env.g(java_pattern).expect("invalid pattern string").as_ptr();
-    // let Ok(mut iterator) = newMidiDev.iter(&mut env) else {
-    //     log::error!("new midi dev failed");
-    //     return;
-    // };
-    //
-    // let mut devices = Vec::new();
-    //
-    // while let Ok(Some(j_obj)) = iterator.next(&mut env) {
-    //     devices.push(
-    //         env.get_string((&j_obj).into())
-    //             .expect("invalid pattern string")
-    //             .to_string_lossy()
-    //             .to_string(),
-    //     );
-    // }
-    //
-    // println!("midi ports = {devices:?}")
+    let mut string = |s: &JString| -> String {
+        env.get_string(s)
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
 
-    let dev = env.get_string(&newMidiDev).expect("invalid pattern string");
+    devices::add(MidiDevice {
+        id: device_id,
+        manufacturer: string(&manufacturer),
+        name: string(&name),
+        inputs: inputs.max(0) as u32,
+        outputs: outputs.max(0) as u32,
+        connected: true,
+    });
+}
 
-    if let Ok(mut devs) = MIDI_DEVS.write() {
-        devs.push(dev.into());
+#[no_mangle]
+pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_midiDeviceRemoved(
+    _env: JNIEnv,
+    _: JClass,
+    device_id: jint,
+) {
+    if let Ok(mut parsers) = JNI_PARSERS.lock() {
+        parsers.remove(&device_id);
     }
 
-    if let Ok(mut devs) = MIDI_DEVS.read() {
-        info!("devices: {devs:?}")
-    }
+    devices::remove(device_id);
 }
 
 #[no_mangle]
@@ -55,6 +101,8 @@ pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_sendMidiMessage(
     mut env: JNIEnv,
     _: JClass,
     message: JByteArray,
+    age_nanos: jlong,
+    device_id: jint,
 ) {
     // // Our Java companion code might pass-in "world" as a string, hence the name.
     // let world = rust_greeting(env.get_string(java_pattern).expect("invalid pattern string").as_ptr());
@@ -65,8 +113,8 @@ pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_sendMidiMessage(
     // output.into_inner()
 
     // let world = env.g(java_pattern).expect("invalid pattern string").as_ptr();
-    println!("midi message = {message:?}");
-    log::info!("midi message = {message:?}");
+    // clock comes 24 times a beat, so anything logged per packet stays below info.
+    log::trace!("midi message = {message:?}");
 
     let bytes: Vec<u8> = match env.get_array_elements(&message, ReleaseMode::NoCopyBack) {
         Ok(elms) => elms
@@ -89,11 +137,20 @@ pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_sendMidiMessage(
         }
     };
 
-    log::info!("bytes = {bytes:?}");
+    log::trace!("bytes = {bytes:?}");
 
-    let message = MidiMessage::from(bytes.as_ref());
+    // the java side passes how long ago the device timestamped the packet, both ends of which
+    // are on the monotonic clock `Instant` uses.
+    let now = Instant::now();
+    let time = now
+        .checked_sub(Duration::from_nanos(age_nanos.max(0) as u64))
+        .unwrap_or(now);
 
-    log::info!("midi message (as enum) = {message:?}");
+    let Ok(mut parsers) = JNI_PARSERS.lock() else {
+        return;
+    };
+    let parser = parsers.entry(device_id).or_default();
+    let device = devices::get(device_id).map(|device| device.source_name().into());
 
     // TAB_SYNTH.lock().map(|synth| {
     //     synth
@@ -116,15 +173,18 @@ pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_sendMidiMessage(
     //     }
     // }
     // CBEAM_CHANNELS.0.send(message);
-    MIDI_SEND.send(message);
-}
-
-#[no_mangle]
-pub unsafe extern "C" fn Java_co_realfit_example_AppMidiManager_clearKnownDevs(
-    mut env: JNIEnv,
-    _: JClass,
-) {
-    if let Ok(mut devs) = MIDI_DEVS.write() {
-        devs.clear();
-    }
+    let source = device.clone().unwrap_or_else(|| "android".into());
+    parser.push_raw(
+        &bytes,
+        |message, raw| {
+            log::debug!("midi message (as enum) = {message:?}");
+            let _ = JNI_MIDI.0.send(Pushed {
+                device: device.clone(),
+                time,
+                msg: message,
+                raw: raw.to_vec(),
+            });
+        },
+        |message| midi::system(&source, message, time),
+    );
 }
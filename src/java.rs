@@ -1,4 +1,4 @@
-use jni::objects::JObject;
+use jni::objects::{GlobalRef, JObject};
 use jni::{AttachGuard, JavaVM};
 
 //
@@ -51,4 +51,57 @@ pub(crate) fn register_midi_callback() {
     if let Err(e) = env.call_method(activity, name, "()V", &[]) {
         log::error!("Error calling instance method {}: {}", name, e);
     }
-}
\ No newline at end of file
+}
+
+/// hands MIDI to the activity's `sendMidiOut`, which writes it to the open output device. the
+/// output thread attaches to the JVM on its first write and stays attached, so nothing is set up
+/// per message.
+pub(crate) struct MidiWriter {
+    vm: JavaVM,
+    activity: GlobalRef,
+}
+
+impl MidiWriter {
+    pub(crate) fn new() -> Option<Self> {
+        let ctx = ndk_context::android_context();
+        let vm = get_vm(&ctx);
+        let activity = {
+            let env = get_env(&vm);
+            let activity = unsafe { JObject::from_raw(ctx.context() as _) };
+            env.new_global_ref(activity)
+        };
+
+        match activity {
+            Ok(activity) => Some(Self { vm, activity }),
+            Err(e) => {
+                log::error!(
+                    "Error keeping a reference to the activity for MIDI out: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub(crate) fn write(&self, bytes: &[u8]) {
+        let mut env = match self.vm.attach_current_thread_permanently() {
+            Ok(env) => env,
+            Err(e) => {
+                log::error!("Error attaching the MIDI out thread: {:?}", e);
+                return;
+            }
+        };
+        let data = match env.byte_array_from_slice(bytes) {
+            Ok(data) => data,
+            Err(e) => {
+                log::error!("Error making a byte array for MIDI out: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = env.call_method(&self.activity, "sendMidiOut", "([B)V", &[(&data).into()]) {
+            log::error!("Error calling instance method sendMidiOut: {}", e);
+        }
+        // the thread never returns to java, so local references are never freed for it.
+        let _ = env.delete_local_ref(data);
+    }
+}